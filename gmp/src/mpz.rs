@@ -63,6 +63,7 @@ extern "C" {
     fn __gmpz_mod(r: mpz_ptr, n: mpz_srcptr, d: mpz_srcptr);
     fn __gmpz_divisible_p(n: mpz_srcptr, d: mpz_srcptr) -> c_int;
     fn __gmpz_legendre(n: mpz_srcptr, d: mpz_srcptr) -> c_int;
+    fn __gmpz_jacobi(a: mpz_srcptr, b: mpz_srcptr) -> c_int;
     fn __gmpz_and(rop: mpz_ptr, op1: mpz_srcptr, op2: mpz_srcptr);
     fn __gmpz_ior(rop: mpz_ptr, op1: mpz_srcptr, op2: mpz_srcptr);
     fn __gmpz_xor(rop: mpz_ptr, op1: mpz_srcptr, op2: mpz_srcptr);
@@ -291,6 +292,12 @@ impl Mpz {
         }
     }
 
+    pub fn jacobi(&self, other: &Mpz) -> i8 {
+        unsafe {
+            __gmpz_jacobi(&self.mpz, &other.mpz) as i8
+        }
+    }
+
     #[inline]
     pub fn divides(&self, other: &Mpz) -> bool {
         other.is_multiple_of(self)
@@ -395,6 +395,14 @@ mod mpz {
         assert!(two.invert(&four).is_none());
     }
 
+    #[test]
+    fn test_jacobi() {
+        let fifteen: Mpz = From::<i64>::from(15);
+        assert!(Mpz::from(2u64).jacobi(&fifteen) == 1);
+        assert!(Mpz::from(7u64).jacobi(&fifteen) == -1);
+        assert!(Mpz::from(5u64).jacobi(&fifteen) == 0);
+    }
+
     #[test]
     fn test_one() {
         let onea: Mpz = From::<i64>::from(1);
@@ -0,0 +1,174 @@
+//! Paillier ciphertext bound to the public key it was encrypted under
+
+use std::ops::{Add, Mul, Neg, Sub};
+use std::ptr;
+use gmp::mpz::Mpz;
+use rng::Randomness;
+use paillier::PaiPk;
+use error::{Error, Result, parse_mpz};
+
+/// A Paillier ciphertext that carries a reference to its public key. The
+/// `try_` methods return errors; the operators are shorthands for them.
+///
+/// # Panics
+///
+/// `+` and `-` between ciphertexts of two different keys panic. So do `-`,
+/// and `+` or `*` with a plaintext, on a ciphertext that is not valid under
+/// its key (only possible when `c` was set directly).
+#[derive(Clone)]
+pub struct Ciphertext<'a> {
+    pub pk : &'a PaiPk,
+    pub c  : Mpz,
+}
+
+impl<'a> Ciphertext<'a> {
+
+    /// `Error::InvalidCiphertext` unless c is a valid ciphertext under pk
+    pub fn new(pk: &'a PaiPk, c: Mpz) -> Result<Ciphertext<'a>> {
+        if !pk.validate_ciphertext(&c) {
+            return Err(Error::InvalidCiphertext);
+        }
+        Ok(Ciphertext { pk, c })
+    }
+
+    /// Parse a ciphertext written in the given radix and validate it
+    pub fn from_str_radix(pk: &'a PaiPk, c: &str, radix: u8) -> Result<Ciphertext<'a>> {
+        Ciphertext::new(pk, parse_mpz(c, radix)?)
+    }
+
+    pub fn encrypt(pk: &'a PaiPk, rs: &mut dyn Randomness, m: &Mpz) -> Result<Ciphertext<'a>> {
+        Ok(Ciphertext { pk, c: pk.encrypt(rs, m)? })
+    }
+
+    /// 0 < c < n^2 and gcd(c, n) = 1
+    pub fn validate(&self) -> bool {
+        self.pk.validate_ciphertext(&self.c)
+    }
+
+    /// `Error::InvalidCiphertext` if c is not a valid ciphertext
+    pub fn rerandomize(&self, rs: &mut dyn Randomness) -> Result<Ciphertext<'a>> {
+        Ok(Ciphertext { pk: self.pk, c: self.pk.rerandomize(rs, &self.c)? })
+    }
+
+    pub fn same_key(&self, pk: &PaiPk) -> bool {
+        ptr::eq(self.pk, pk) || self.pk.n == pk.n
+    }
+
+    /// `Error::KeyMismatch` unless the ciphertext belongs to pk
+    pub fn check_key(&self, pk: &PaiPk) -> Result<()> {
+        if !self.same_key(pk) {
+            return Err(Error::KeyMismatch);
+        }
+        Ok(())
+    }
+
+    /// Encryption of the sum, `Error::KeyMismatch` for different keys
+    pub fn try_add(&self, other: &Ciphertext<'a>) -> Result<Ciphertext<'a>> {
+        self.check_key(other.pk)?;
+        Ok(Ciphertext { pk: self.pk, c: (&self.c * &other.c) % &self.pk.n2 })
+    }
+
+    /// Encryption of the difference, `Error::KeyMismatch` for different keys
+    pub fn try_sub(&self, other: &Ciphertext<'a>) -> Result<Ciphertext<'a>> {
+        self.check_key(other.pk)?;
+        self.try_add(&other.try_neg()?)
+    }
+
+    /// Encryption of -m, `Error::InvalidCiphertext` if c is not a unit mod n^2
+    pub fn try_neg(&self) -> Result<Ciphertext<'a>> {
+        let c = self.c.invert(&self.pk.n2).ok_or(Error::InvalidCiphertext)?;
+        Ok(Ciphertext { pk: self.pk, c })
+    }
+
+    /// Encryption of the sum with a plaintext m, reduced mod n.
+    /// `Error::InvalidCiphertext` if c is not a valid ciphertext.
+    pub fn try_add_const(&self, m: &Mpz) -> Result<Ciphertext<'a>> {
+        Ok(Ciphertext { pk: self.pk, c: self.pk.add_const(&self.c, m)? })
+    }
+
+    /// Encryption of the product with a plaintext m, reduced mod n.
+    /// `Error::InvalidCiphertext` if c is not a valid ciphertext.
+    pub fn try_mul_const(&self, m: &Mpz) -> Result<Ciphertext<'a>> {
+        Ok(Ciphertext { pk: self.pk, c: self.pk.mul_const(&self.c, m)? })
+    }
+}
+
+impl<'a, 'b> Add<&'b Ciphertext<'a>> for &'b Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn add(self, other: &Ciphertext<'a>) -> Ciphertext<'a> {
+        self.try_add(other).expect("cannot add ciphertexts")
+    }
+}
+
+impl<'a> Add<Ciphertext<'a>> for Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn add(self, other: Ciphertext<'a>) -> Ciphertext<'a> {
+        &self + &other
+    }
+}
+
+impl<'a, 'b> Add<&'b Mpz> for &'b Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn add(self, m: &Mpz) -> Ciphertext<'a> {
+        self.try_add_const(m).expect("cannot add a plaintext to an invalid ciphertext")
+    }
+}
+
+impl<'a> Add<&Mpz> for Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn add(self, m: &Mpz) -> Ciphertext<'a> {
+        &self + m
+    }
+}
+
+impl<'a, 'b> Mul<&'b Mpz> for &'b Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn mul(self, m: &Mpz) -> Ciphertext<'a> {
+        self.try_mul_const(m).expect("cannot multiply an invalid ciphertext")
+    }
+}
+
+impl<'a> Mul<&Mpz> for Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn mul(self, m: &Mpz) -> Ciphertext<'a> {
+        &self * m
+    }
+}
+
+impl<'a> Neg for &Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn neg(self) -> Ciphertext<'a> {
+        self.try_neg().expect("ciphertext is not invertible mod n^2")
+    }
+}
+
+impl<'a> Neg for Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn neg(self) -> Ciphertext<'a> {
+        -&self
+    }
+}
+
+impl<'a, 'b> Sub<&'b Ciphertext<'a>> for &'b Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn sub(self, other: &Ciphertext<'a>) -> Ciphertext<'a> {
+        self.try_sub(other).expect("cannot subtract ciphertexts")
+    }
+}
+
+impl<'a> Sub<Ciphertext<'a>> for Ciphertext<'a> {
+    type Output = Ciphertext<'a>;
+
+    fn sub(self, other: Ciphertext<'a>) -> Ciphertext<'a> {
+        &self - &other
+    }
+}
@@ -0,0 +1,172 @@
+//! Damgård-Jurik cryptosystem, Paillier generalized to ciphertexts in
+//! Z_{n^(s+1)} and plaintexts in Z_{n^s}. s = 1 is plain Paillier.
+
+use gmp::mpz::Mpz;
+use rng::{generate_strong_prime, check_key_size, Randomness, RandomSource};
+use error::{Error, Result};
+use zk::modulus::{has_small_factor, SMALL_PRIME_BOUND};
+
+pub struct DjSk {
+    pub lambda : Mpz,
+    pub mu     : Mpz,   // lambda^-1 mod n^s
+}
+
+#[derive(Clone)]
+pub struct DjPk {
+    pub n   : Mpz,
+    pub s   : u32,
+    pub ns  : Mpz,   // n^s
+    pub ns1 : Mpz,   // n^(s+1)
+    pub g   : Mpz,
+}
+
+pub struct DamgardJurik {
+    pub pk : DjPk,
+    pub sk : DjSk,
+    pub rs : Box<dyn Randomness>,
+}
+
+fn factorial(k: u32) -> Mpz {
+    (2..k + 1).fold(Mpz::one(), |acc, i| acc * (i as u64))
+}
+
+impl DjPk {
+
+    /// `Error::InvalidKeySize` if s is 0 or not below `SMALL_PRIME_BOUND`,
+    /// `Error::InvalidKey` if n has a prime factor below the bound. The
+    /// extraction in `dlog` divides by k! for k <= s, so no prime up to s may
+    /// divide n.
+    pub fn new(n: Mpz, s: u32) -> Result<DjPk> {
+        if s == 0 || s as u64 >= SMALL_PRIME_BOUND {
+            return Err(Error::InvalidKeySize);
+        }
+        if has_small_factor(&n) {
+            return Err(Error::InvalidKey);
+        }
+        let ns = n.pow(s);
+        let ns1 = &ns * &n;
+        let g = &n + Mpz::one();
+        Ok(DjPk { n, s, ns, ns1, g })
+    }
+
+    /// 0 < c < n^(s+1) and gcd(c, n) = 1
+    pub fn validate_ciphertext(&self, c: &Mpz) -> bool {
+        *c > Mpz::zero() && *c < self.ns1 && c.gcd(&self.n) == Mpz::one()
+    }
+
+    /// Encrypt m in [0, n^s), `Error::PlaintextOverflow` otherwise
+    pub fn encrypt(&self, rs: &mut dyn Randomness, m: &Mpz) -> Result<Mpz> {
+        if *m < Mpz::zero() || *m >= self.ns {
+            return Err(Error::PlaintextOverflow);
+        }
+        let mut r = rs.urandom(&self.n);
+        while r.gcd(&self.n) != Mpz::one() {
+            r = rs.urandom(&self.n);
+        }
+
+        let rns = r.powm(&self.ns, &self.ns1);
+        let gm = self.g.powm(m, &self.ns1);
+        Ok((&gm * &rns) % &self.ns1)
+    }
+
+    pub fn add_cipher(&self, c1: &Mpz, c2: &Mpz) -> Mpz {
+        (c1 * c2) % &self.ns1
+    }
+
+    /// m may be negative, it is reduced mod n^s
+    pub fn add_const(&self, c: &Mpz, m: &Mpz) -> Mpz {
+        self.add_cipher(c, &self.g.powm(&m.modulus(&self.ns), &self.ns1))
+    }
+
+    /// m may be negative, it is reduced mod n^s
+    pub fn mul_const(&self, c: &Mpz, m: &Mpz) -> Mpz {
+        c.powm(&m.modulus(&self.ns), &self.ns1)
+    }
+
+    /// Recover i from a = (1+n)^i mod n^(s+1), the recursive extraction of
+    /// Damgård and Jurik. `Error::NotInvertible` if n shares a factor with
+    /// some k <= s.
+    pub fn dlog(&self, a: &Mpz) -> Result<Mpz> {
+        let mut i = Mpz::zero();
+        let mut nj = self.n.clone();        // n^j
+        for j in 1..self.s + 1 {
+            let nj1 = &nj * &self.n;        // n^(j+1)
+            let mut t1 = ((a % &nj1) - Mpz::one()) / &self.n;
+            let mut t2 = i.clone();
+            let mut nk = self.n.clone();    // n^(k-1)
+            for k in 2..j + 1 {
+                i = i - Mpz::one();
+                t2 = (&t2 * &i).modulus(&nj);
+                let kinv = factorial(k).invert(&nj).ok_or(Error::NotInvertible)?;
+                t1 = (t1 - &t2 * &nk * kinv).modulus(&nj);
+                nk = nk * &self.n;
+            }
+            i = t1;
+            nj = nj1;
+        }
+        Ok(i)
+    }
+}
+
+impl DjSk {
+
+    pub fn decrypt(&self, pk: &DjPk, c: &Mpz) -> Result<Mpz> {
+        if !pk.validate_ciphertext(c) {
+            return Err(Error::InvalidCiphertext);
+        }
+        let cl = c.powm(&self.lambda, &pk.ns1);
+        Ok((pk.dlog(&cl)? * &self.mu) % &pk.ns)
+    }
+}
+
+impl DamgardJurik {
+
+    /// `Error::InvalidKeySize` unless keysize is even and at least
+    /// `MIN_KEY_SIZE` and s is at least 1
+    pub fn new(keysize: usize, s: u32) -> Result<DamgardJurik> {
+        DamgardJurik::with_rng(keysize, s, Box::new(RandomSource::new()?))
+    }
+
+    /// Generate the key with `rs` and keep it for later encryptions
+    pub fn with_rng(keysize: usize, s: u32, mut rs: Box<dyn Randomness>) -> Result<DamgardJurik> {
+        let (pk, sk) = DamgardJurik::generate_key(&mut *rs, keysize, s)?;
+
+        Ok(DamgardJurik { pk, sk, rs })
+    }
+
+    fn generate_key(randstate: &mut dyn Randomness, keysize: usize, s: u32) -> Result<(DjPk, DjSk)> {
+        check_key_size(keysize)?;
+
+        let p = generate_strong_prime(randstate, keysize/2);
+        let mut q = generate_strong_prime(randstate, keysize/2);
+        while p == q {
+            q = generate_strong_prime(randstate, keysize/2);
+        }
+
+        let pk = DjPk::new(&p * &q, s)?;
+        let lambda = (&p - Mpz::one()) * (&q - Mpz::one());
+        let mu = lambda.invert(&pk.ns).ok_or(Error::NotInvertible)?;
+
+        Ok((pk, DjSk { lambda, mu }))
+    }
+
+    pub fn encrypt(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt(&mut self.rs, m)
+    }
+
+    pub fn decrypt(&self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt(&self.pk, c)
+    }
+
+    pub fn add_cipher(&self, c1: &Mpz, c2: &Mpz) -> Mpz {
+        self.pk.add_cipher(c1, c2)
+    }
+
+    pub fn add_const(&self, c: &Mpz, m: &Mpz) -> Mpz {
+        self.pk.add_const(c, m)
+    }
+
+    pub fn mul_const(&self, c: &Mpz, m: &Mpz) -> Mpz {
+        self.pk.mul_const(c, m)
+    }
+}
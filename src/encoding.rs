@@ -0,0 +1,178 @@
+//! Plaintext encodings for Paillier
+
+use gmp::mpz::Mpz;
+use paillier::PaiPk;
+use error::{Error, Result};
+
+/// Largest magnitude a signed plaintext may have, floor(n/3)
+pub fn max_signed(pk: &PaiPk) -> Mpz {
+    &pk.n / 3u64
+}
+
+/// Map an integer in [-n/3, n/3] into Z_n, negative values wrap to the top of Z_n
+pub fn encode_signed(pk: &PaiPk, m: &Mpz) -> Result<Mpz> {
+    if m.abs() > max_signed(pk) {
+        return Err(Error::PlaintextOverflow);
+    }
+    Ok(m.modulus(&pk.n))
+}
+
+/// Inverse of `encode_signed`. Values in the band (n/3, n - n/3) can only be
+/// reached by overflow, they are rejected with `Error::PlaintextOverflow`.
+pub fn decode_signed(pk: &PaiPk, m: &Mpz) -> Result<Mpz> {
+    let max = max_signed(pk);
+    if *m <= max {
+        Ok(m.clone())
+    } else if *m >= &pk.n - &max {
+        Ok(m - &pk.n)
+    } else {
+        Err(Error::PlaintextOverflow)
+    }
+}
+
+/// Base of the public exponent of `EncodedNumber`, same as python-paillier
+pub const BASE: u64 = 16;
+
+/// A rational number mantissa * BASE^exponent, with the signed mantissa
+/// encoded in Z_n by `encode_signed` and the exponent left public
+#[derive(Clone)]
+pub struct EncodedNumber {
+    pub encoding : Mpz,
+    pub exponent : i32,
+}
+
+/// Encryption of the mantissa of an `EncodedNumber`, the exponent is public
+#[derive(Clone)]
+pub struct EncryptedNumber {
+    pub ciphertext : Mpz,
+    pub exponent   : i32,
+}
+
+fn base_pow(e: u32) -> Mpz {
+    Mpz::from(BASE).pow(e)
+}
+
+/// BASE^(from - to), `Error::PlaintextOverflow` unless 4 * (from - to) < bits(n)
+/// so that the factor stays below n
+fn exponent_factor(pk: &PaiPk, from: i32, to: i32) -> Result<Mpz> {
+    let diff = from.checked_sub(to).ok_or(Error::PlaintextOverflow)? as u64;
+    if 4 * diff >= pk.n.bit_length() as u64 {
+        return Err(Error::PlaintextOverflow);
+    }
+    Ok(base_pow(diff as u32))
+}
+
+/// x * 2^e without going through an intermediate that under- or overflows
+fn scale_f64(mut x: f64, mut e: i64) -> f64 {
+    while e != 0 && x != 0.0 && x.is_finite() {
+        let step = e.clamp(-1000, 1000);
+        x *= 2f64.powi(step as i32);
+        e -= step;
+    }
+    x
+}
+
+impl EncodedNumber {
+
+    /// Encode the signed integer mantissa, `Error::PlaintextOverflow` if it
+    /// does not fit in [-n/3, n/3]
+    pub fn new(pk: &PaiPk, mantissa: &Mpz, exponent: i32) -> Result<EncodedNumber> {
+        encode_signed(pk, mantissa).map(|encoding| EncodedNumber { encoding, exponent })
+    }
+
+    /// Exact encoding of a finite f64, `Error::InvalidPlaintext` for NaN and infinities
+    pub fn encode_f64(pk: &PaiPk, x: f64) -> Result<EncodedNumber> {
+        if !x.is_finite() {
+            return Err(Error::InvalidPlaintext);
+        }
+        if x == 0.0 {
+            return EncodedNumber::new(pk, &Mpz::zero(), 0);
+        }
+
+        // |x| = mant * 2^e
+        let bits = x.to_bits();
+        let exp_bits = ((bits >> 52) & 0x7ff) as i32;
+        let frac = bits & ((1u64 << 52) - 1);
+        let (mut mant, e) = if exp_bits == 0 {
+            (frac, -1074)
+        } else {
+            (frac | (1u64 << 52), exp_bits - 1075)
+        };
+
+        // move to base 16 and strip trailing zero digits
+        let mut exponent = e.div_euclid(4);
+        mant <<= e - 4 * exponent;
+        while mant % BASE == 0 {
+            mant /= BASE;
+            exponent += 1;
+        }
+
+        let mut mantissa = Mpz::from(mant);
+        if x < 0.0 {
+            mantissa = -mantissa;
+        }
+        EncodedNumber::new(pk, &mantissa, exponent)
+    }
+
+    /// Signed mantissa, `Error::PlaintextOverflow` if it lies in the overflow band
+    pub fn mantissa(&self, pk: &PaiPk) -> Result<Mpz> {
+        decode_signed(pk, &self.encoding)
+    }
+
+    pub fn decode_f64(&self, pk: &PaiPk) -> Result<f64> {
+        self.mantissa(pk).map(|m| {
+            let x: f64 = (&m).into();
+            scale_f64(x, 4 * self.exponent as i64)
+        })
+    }
+
+    /// Same value with a smaller exponent, `Error::InvalidPlaintext` if
+    /// new_exp is larger and `Error::PlaintextOverflow` if the mantissa would overflow
+    pub fn decrease_exponent_to(&self, pk: &PaiPk, new_exp: i32) -> Result<EncodedNumber> {
+        if new_exp > self.exponent {
+            return Err(Error::InvalidPlaintext);
+        }
+        let factor = exponent_factor(pk, self.exponent, new_exp)?;
+        EncodedNumber::new(pk, &(self.mantissa(pk)? * factor), new_exp)
+    }
+}
+
+impl EncryptedNumber {
+
+    /// Same value with a smaller exponent. The mantissa is multiplied under
+    /// encryption, overflow is only detected at decryption.
+    /// `Error::InvalidPlaintext` if new_exp is larger than the current exponent,
+    /// `Error::PlaintextOverflow` if BASE^(exponent - new_exp) does not fit below n.
+    pub fn decrease_exponent_to(&self, pk: &PaiPk, new_exp: i32) -> Result<EncryptedNumber> {
+        if new_exp > self.exponent {
+            return Err(Error::InvalidPlaintext);
+        }
+        let factor = exponent_factor(pk, self.exponent, new_exp)?;
+        Ok(EncryptedNumber { ciphertext: pk.mul_const(&self.ciphertext, &factor)?, exponent: new_exp })
+    }
+
+    pub fn add(&self, pk: &PaiPk, other: &EncryptedNumber) -> Result<EncryptedNumber> {
+        let exponent = if self.exponent < other.exponent { self.exponent } else { other.exponent };
+        let a = self.decrease_exponent_to(pk, exponent)?;
+        let b = other.decrease_exponent_to(pk, exponent)?;
+        Ok(EncryptedNumber { ciphertext: pk.add_cipher(&a.ciphertext, &b.ciphertext)?, exponent })
+    }
+
+    pub fn add_encoded(&self, pk: &PaiPk, other: &EncodedNumber) -> Result<EncryptedNumber> {
+        let mut a = self.clone();
+        let mut b = other.clone();
+        if a.exponent > b.exponent {
+            a = a.decrease_exponent_to(pk, b.exponent)?;
+        } else if b.exponent > a.exponent {
+            b = b.decrease_exponent_to(pk, a.exponent)?;
+        }
+        Ok(EncryptedNumber { ciphertext: pk.add_const(&a.ciphertext, &b.encoding)?, exponent: a.exponent })
+    }
+
+    pub fn mul_encoded(&self, pk: &PaiPk, other: &EncodedNumber) -> Result<EncryptedNumber> {
+        Ok(EncryptedNumber {
+            ciphertext: pk.mul_const(&self.ciphertext, &other.encoding)?,
+            exponent: self.exponent.checked_add(other.exponent).ok_or(Error::PlaintextOverflow)?,
+        })
+    }
+}
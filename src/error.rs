@@ -0,0 +1,72 @@
+//! Errors returned by the library
+
+use gmp::mpz::Mpz;
+use std::error;
+use std::fmt;
+use std::result;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// Key size is odd or too small
+    InvalidKeySize,
+    /// Public key parameters are malformed
+    InvalidKey,
+    /// Ciphertext is out of range or not a unit
+    InvalidCiphertext,
+    /// Plaintext cannot be encoded, e.g. a NaN
+    InvalidPlaintext,
+    /// Ciphertext belongs to a different key
+    KeyMismatch,
+    /// Plaintext does not fit in the plaintext space
+    PlaintextOverflow,
+    /// String is not a valid number in the given radix
+    Parse,
+    /// Element has no modular inverse
+    NotInvertible,
+    /// Encryption randomness is not a unit modulo n
+    InvalidRandomness,
+    /// Threshold t is not in [1, l]
+    InvalidThreshold,
+    /// Fewer valid decryption shares than the threshold
+    NotEnoughShares,
+    /// Randomness pool is dry and its policy forbids computing more
+    PoolEmpty,
+    /// A background producer is still filling the randomness pool
+    PoolBusy,
+    /// Operating system entropy source is unavailable
+    Entropy,
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            Error::InvalidKeySize => "invalid key size",
+            Error::InvalidKey => "invalid public key",
+            Error::InvalidCiphertext => "invalid ciphertext",
+            Error::InvalidPlaintext => "invalid plaintext",
+            Error::KeyMismatch => "ciphertext belongs to a different key",
+            Error::PlaintextOverflow => "plaintext overflow",
+            Error::Parse => "cannot parse number",
+            Error::NotInvertible => "element is not invertible",
+            Error::InvalidRandomness => "randomness is not a unit modulo n",
+            Error::InvalidThreshold => "invalid threshold",
+            Error::NotEnoughShares => "not enough valid decryption shares",
+            Error::PoolEmpty => "randomness pool is empty",
+            Error::PoolBusy => "randomness pool producer is still running",
+            Error::Entropy => "cannot read operating system entropy",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl error::Error for Error {}
+
+/// Parse an integer in the given radix, `Error::Parse` on bad input or radix
+pub fn parse_mpz(s: &str, radix: u8) -> Result<Mpz> {
+    if !(2..=62).contains(&radix) {
+        return Err(Error::Parse);
+    }
+    Mpz::from_str_radix(s, radix).map_err(|()| Error::Parse)
+}
@@ -1,10 +1,9 @@
 /// Goldwasser-Micali cryptosystem
 
 use gmp;
-use gmp::rand::RandState;
-use rand::Rng;
-use rand;
-use rng::generate_strong_prime;
+use rng::{generate_strong_prime, check_key_size, Randomness, RandomSource, HashDrbg};
+use error::{Error, Result, parse_mpz};
+use zk::modulus::has_small_factor;
 
 pub type Mpz = gmp::mpz::Mpz;
 
@@ -22,29 +21,116 @@ pub struct GmPk {
 pub struct GM {
     pub pk : GmPk,
     pub sk : GmSk,
-    pub rs : RandState,
+    pub rs : Box<dyn Randomness>,
+}
+
+impl GmPk {
+
+    /// Parse n and x written in the given radix, n must be odd and above 1 and
+    /// x a valid ciphertext
+    pub fn from_str_radix(n: &str, x: &str, radix: u8) -> Result<GmPk> {
+        let n = parse_mpz(n, radix)?;
+        let x = parse_mpz(x, radix)?;
+        if !n.tstbit(0) || n <= Mpz::one() {
+            return Err(Error::InvalidKey);
+        }
+        let pk = GmPk { n, x };
+        if !pk.validate_ciphertext(&pk.x) {
+            return Err(Error::InvalidKey);
+        }
+        Ok(pk)
+    }
+
+    /// Check that n is odd, at least keysize bits and free of small factors,
+    /// and that x is a unit with Jacobi symbol 1
+    pub fn validate(&self, keysize: usize) -> bool {
+        self.n.tstbit(0) && self.n.bit_length() >= keysize
+            && !has_small_factor(&self.n)
+            && self.validate_ciphertext(&self.x)
+    }
+
+    /// 0 < c < n, gcd(c, n) = 1 and Jacobi symbol (c/n) = 1
+    pub fn validate_ciphertext(&self, c: &Mpz) -> bool {
+        *c > Mpz::zero() && *c < self.n && c.gcd(&self.n) == Mpz::one()
+            && c.jacobi(&self.n) == 1
+    }
+
+    /// Sample y uniformly from the units of Z_n
+    pub fn random_unit(&self, rs: &mut dyn Randomness) -> Mpz {
+        let mut y = rs.urandom(&self.n);
+        while  y.gcd(&self.n) != Mpz::one() {
+            y = rs.urandom(&self.n)
+        }
+        y
+    }
+
+    /// y^2 * x^m mod n for a fresh unit y
+    pub fn encrypt(&self, rs: &mut dyn Randomness, m: bool) -> Mpz {
+        self.encrypt_returning_randomness(rs, m).0
+    }
+
+    /// Encrypt m and also return the nonce y
+    pub fn encrypt_returning_randomness(&self, rs: &mut dyn Randomness, m: bool) -> (Mpz, Mpz) {
+        let y = self.random_unit(rs);
+        let c = self.encrypt_unchecked(m, &y);
+        (c, y)
+    }
+
+    /// Deterministic y^2 * x^m mod n with a caller-supplied nonce.
+    /// `Error::InvalidRandomness` unless 0 < y < n and gcd(y, n) = 1.
+    pub fn encrypt_with_randomness(&self, m: bool, y: &Mpz) -> Result<Mpz> {
+        if *y <= Mpz::zero() || *y >= self.n || y.gcd(&self.n) != Mpz::one() {
+            return Err(Error::InvalidRandomness);
+        }
+        Ok(self.encrypt_unchecked(m, y))
+    }
+
+    fn encrypt_unchecked(&self, m: bool, y: &Mpz) -> Mpz {
+        let y2 = (y * y) % &self.n;
+        if m { (y2 * &self.x) % &self.n } else { y2 }
+    }
+
+    /// Multiply by a fresh y^2 mod n, the result cannot be linked to c.
+    /// `Error::InvalidCiphertext` if c is not a valid ciphertext.
+    pub fn rerandomize(&self, rs: &mut dyn Randomness, c: &Mpz) -> Result<Mpz> {
+        if !self.validate_ciphertext(c) {
+            return Err(Error::InvalidCiphertext);
+        }
+        let y = self.random_unit(rs);
+        Ok((c * (&y * &y)) % &self.n)
+    }
 }
 
 impl GM {
 
-    pub fn new(keysize: usize) -> GM {
-        let mut rng = rand::thread_rng();
-        let mut randstate = RandState::new();
-        randstate.seed_ui( rng.gen::<u64>() );
-        let (pk, sk) = GM::generate_key(&mut randstate, keysize);
+    /// `Error::InvalidKeySize` unless keysize is even and at least `MIN_KEY_SIZE`
+    pub fn new(keysize: usize) -> Result<GM> {
+        GM::with_rng(keysize, Box::new(RandomSource::new()?))
+    }
+
+    /// Generate the key with `rs` and keep it for later encryptions
+    pub fn with_rng(keysize: usize, mut rs: Box<dyn Randomness>) -> Result<GM> {
+        let (pk, sk) = GM::generate_key(&mut *rs, keysize)?;
 
-        GM { pk: pk, sk: sk, rs: randstate }
+        Ok(GM { pk: pk, sk: sk, rs: rs })
     }
 
-    fn generate_key(mut randstate: &mut RandState, keysize: usize) -> (GmPk, GmSk) {
-        assert!(keysize % 2 == 0);
+    /// Derive the key, and every later encryption nonce, from `seed` through
+    /// a `HashDrbg`. Anyone who knows the seed knows the key: for test
+    /// fixtures only.
+    pub fn from_seed(keysize: usize, seed: &[u8]) -> Result<GM> {
+        GM::with_rng(keysize, Box::new(HashDrbg::new(seed)))
+    }
+
+    fn generate_key(mut randstate: &mut dyn Randomness, keysize: usize) -> Result<(GmPk, GmSk)> {
+        check_key_size(keysize)?;
         let p = generate_strong_prime(&mut randstate, keysize/2 + 1);
         let mut q = generate_strong_prime(&mut randstate, keysize/2);
         while p == q {
             q = generate_strong_prime(&mut randstate, keysize/2);
         }
+        // p has keysize/2 + 1 bits, so n has at least keysize bits
         let n = &p*&q;
-        assert!( n.bit_length() >= keysize );
         let mut x : Mpz;
         loop {
             x = randstate.urandom(&n);
@@ -53,26 +139,36 @@ impl GM {
             }
         }
 
-        (GmPk {n: n, x: x}, GmSk {p: p, q: q} )
+        Ok((GmPk {n: n, x: x}, GmSk {p: p, q: q}))
     }
 
     pub fn encrypt(&mut self, m: bool) -> Mpz {
+        self.pk.encrypt(&mut self.rs, m)
+    }
+
+    pub fn encrypt_with_randomness(&self, m: bool, y: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt_with_randomness(m, y)
+    }
 
-        let mm = if m {Mpz::one()} else {Mpz::zero()};
+    pub fn encrypt_returning_randomness(&mut self, m: bool) -> (Mpz, Mpz) {
+        self.pk.encrypt_returning_randomness(&mut self.rs, m)
+    }
 
-        let mut y = self.rs.urandom(&self.pk.n);
-        while  y.gcd(&self.pk.n) != Mpz::one() {
-            y = self.rs.urandom(&self.pk.n)
+    pub fn decrypt(&mut self, c: &Mpz) -> Result<bool> {
+        if !self.pk.validate_ciphertext(c) {
+            return Err(Error::InvalidCiphertext);
         }
-
-        (&y * &y) * self.pk.x.powm(&mm, &self.pk.n) % &self.pk.n
+        Ok(c.legendre(&self.sk.p) != 1 && c.legendre(&self.sk.q) != 1)
     }
 
-    pub fn decrypt(&mut self, c: &Mpz) -> bool {
-        c.legendre(&self.sk.p) != 1 && c.legendre(&self.sk.q) != 1
+    pub fn rerandomize(&mut self, c: &Mpz) -> Result<Mpz> {
+        self.pk.rerandomize(&mut self.rs, c)
     }
 
-    pub fn xor(&mut self, c1: &Mpz, c2: &Mpz) -> Mpz {
-        ( c1 * c2 ) %  &self.pk.n
+    pub fn xor(&mut self, c1: &Mpz, c2: &Mpz) -> Result<Mpz> {
+        if !self.pk.validate_ciphertext(c1) || !self.pk.validate_ciphertext(c2) {
+            return Err(Error::InvalidCiphertext);
+        }
+        Ok(( c1 * c2 ) %  &self.pk.n)
     }
 }
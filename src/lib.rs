@@ -2,10 +2,19 @@
 extern crate gmp;
 extern crate test;
 extern crate rand;
+extern crate sha2;
 
+pub mod ciphertext;
+pub mod damgard_jurik;
+pub mod encoding;
+pub mod error;
 pub mod gm;
+pub mod packing;
 pub mod paillier;
+pub mod pool;
 pub mod rng;
+pub mod threshold;
+pub mod zk;
 
 #[cfg(test)]
 pub mod tests;
@@ -0,0 +1,97 @@
+//! Packing of many small unsigned integers into one Paillier plaintext
+//!
+//! Each lane is `value_bits + headroom_bits + 1` bits wide. Packed ciphertexts
+//! add lane-wise with `add_cipher`; up to 2^headroom_bits of them can be summed
+//! safely. `unpack_sum` takes the number of summed plaintexts and rejects sums
+//! that could outgrow the headroom. The top bit of every lane is a guard bit
+//! that catches a lane overflowing by one more bit, the only check `unpack`
+//! can do without the count.
+
+use gmp::mpz::Mpz;
+use paillier::PaiPk;
+use error::{Error, Result};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Packer {
+    pub lanes         : usize,
+    pub value_bits    : usize,
+    pub headroom_bits : usize,
+}
+
+impl Packer {
+
+    /// As many lanes as fit in the plaintext space of pk, `None` if a lane
+    /// does not fit in a u64 or not even one lane fits
+    pub fn new(pk: &PaiPk, value_bits: usize, headroom_bits: usize) -> Option<Packer> {
+        let lane_bits = value_bits + headroom_bits + 1;
+        Packer::with_lanes(pk, (pk.n.bit_length() - 1) / lane_bits, value_bits, headroom_bits)
+    }
+
+    pub fn with_lanes(pk: &PaiPk, lanes: usize, value_bits: usize, headroom_bits: usize) -> Option<Packer> {
+        let packer = Packer { lanes, value_bits, headroom_bits };
+        if value_bits == 0 || packer.lane_bits() > 64 || lanes == 0 {
+            return None;
+        }
+        // keep the packed plaintext strictly below n
+        if lanes * packer.lane_bits() > pk.n.bit_length() - 1 {
+            return None;
+        }
+        Some(packer)
+    }
+
+    pub fn lane_bits(&self) -> usize {
+        self.value_bits + self.headroom_bits + 1
+    }
+
+    /// Lane i of the result holds values[i], missing lanes are zero.
+    /// `Error::PlaintextOverflow` if there are too many values or one of them
+    /// needs more than value_bits.
+    pub fn pack(&self, values: &[u64]) -> Result<Mpz> {
+        if values.len() > self.lanes {
+            return Err(Error::PlaintextOverflow);
+        }
+        let mut m = Mpz::zero();
+        for &v in values.iter().rev() {
+            if self.value_bits < 64 && v >> self.value_bits != 0 {
+                return Err(Error::PlaintextOverflow);
+            }
+            m = (m << self.lane_bits()) + Mpz::from(v);
+        }
+        Ok(m)
+    }
+
+    /// Split the decrypted sum of `count` packed plaintexts into its lanes,
+    /// `Error::PlaintextOverflow` if more than 2^headroom_bits were summed or
+    /// a lane overflowed
+    pub fn unpack_sum(&self, m: &Mpz, count: usize) -> Result<Vec<u64>> {
+        if count as u64 > 1u64 << self.headroom_bits {
+            return Err(Error::PlaintextOverflow);
+        }
+        self.unpack(m)
+    }
+
+    /// Split a decrypted plaintext into its lanes, `Error::PlaintextOverflow`
+    /// if a guard bit is set or the top lane overflowed. A lane that overflowed
+    /// by more than one bit carries into the next one unnoticed, use
+    /// `unpack_sum` for sums.
+    pub fn unpack(&self, m: &Mpz) -> Result<Vec<u64>> {
+        let w = self.lane_bits();
+        if m.bit_length() > self.lanes * w {
+            return Err(Error::PlaintextOverflow);
+        }
+        let mut mask = Mpz::zero();
+        mask.setbit(w);
+        mask = mask - Mpz::one();
+
+        let mut values = Vec::with_capacity(self.lanes);
+        for i in 0..self.lanes {
+            if m.tstbit(i * w + w - 1) {
+                return Err(Error::PlaintextOverflow);
+            }
+            let lane = (m >> (i * w)) & &mask;
+            let v: Option<u64> = (&lane).into();
+            values.push(v.unwrap());
+        }
+        Ok(values)
+    }
+}
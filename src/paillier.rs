@@ -1,16 +1,37 @@
 /// Paillier cryptosystem
 
 use gmp::mpz::Mpz;
-use gmp::rand::RandState;
-use rand::Rng;
-use rand;
-use rng::generate_strong_prime;
+use rng::{generate_blum_prime, check_key_size, Randomness, RandomSource, HashDrbg};
+use error::{Error, Result, parse_mpz};
+use ciphertext::Ciphertext;
+use zk::modulus::{has_small_factor, ModulusProof};
+use encoding::{encode_signed, decode_signed, EncodedNumber, EncryptedNumber};
 
 pub struct PaiSk {
     pub lambda : Mpz,
     pub mu     : Mpz,
+    pub p      : Mpz,
+    pub q      : Mpz,
+    pub p2     : Mpz,
+    pub q2     : Mpz,
+    pub hp     : Mpz,
+    pub hq     : Mpz,
+    pub pinv   : Mpz,   // p^-1 mod q
+    pub ln     : FastL,
+    pub lp     : FastL,
+    pub lq     : FastL,
+    pub np     : Mpz,   // n mod p(p-1)
+    pub nq     : Mpz,   // n mod q(q-1)
+    pub p2inv  : Mpz,   // p^-2 mod q^2
 }
 
+/// L(u) = (u-1)/n computed as (u-1) * n^-1 mod 2^k, where n^-1 is precomputed
+pub struct FastL {
+    pub ninv  : Mpz,
+    pub two_k : Mpz,
+}
+
+#[derive(Clone)]
 pub struct PaiPk {
     pub n : Mpz,
     pub n2 : Mpz,
@@ -20,63 +41,475 @@ pub struct PaiPk {
 pub struct Paillier {
     pub pk : PaiPk,
     pub sk : PaiSk,
-    pub rs : RandState,
+    pub rs : Box<dyn Randomness>,
+}
+
+/// Encrypting side of Paillier, needs only the public key
+pub struct PaiEncryptor {
+    pub pk : PaiPk,
+    pub rs : Box<dyn Randomness>,
+}
+
+/// Decrypting side of Paillier, holds the secret key
+pub struct PaiDecryptor {
+    pub pk : PaiPk,
+    pub sk : PaiSk,
+}
+
+impl PaiPk {
+
+    /// Public key with the standard generator g = n+1
+    pub fn from_n(n: Mpz) -> PaiPk {
+        let n2 = &n * &n;
+        let g = &n + Mpz::one();
+        PaiPk { n, n2, g }
+    }
+
+    /// Parse a modulus written in the given radix, n must be odd and above 1
+    pub fn from_str_radix(n: &str, radix: u8) -> Result<PaiPk> {
+        let n = parse_mpz(n, radix)?;
+        if !n.tstbit(0) || n <= Mpz::one() {
+            return Err(Error::InvalidKey);
+        }
+        Ok(PaiPk::from_n(n))
+    }
+
+    /// Import a counterparty's modulus, `Error::InvalidKey` unless the proof
+    /// that it is a square-free Paillier-Blum modulus verifies
+    pub fn import(n: Mpz, proof: &ModulusProof, context: &[u8]) -> Result<PaiPk> {
+        if proof.verify(&n, context) {
+            Ok(PaiPk::from_n(n))
+        } else {
+            Err(Error::InvalidKey)
+        }
+    }
+
+    /// Check that n is odd, of keysize bits and free of small factors, and
+    /// that n2 and g are the values derived from it
+    pub fn validate(&self, keysize: usize) -> bool {
+        self.n.tstbit(0) && self.n.bit_length() == keysize
+            && self.n2 == &self.n * &self.n
+            && self.g == &self.n + Mpz::one()
+            && !has_small_factor(&self.n)
+    }
+
+    /// 0 < c < n^2 and gcd(c, n) = 1
+    pub fn validate_ciphertext(&self, c: &Mpz) -> bool {
+        *c > Mpz::zero() && *c < self.n2 && c.gcd(&self.n) == Mpz::one()
+    }
+
+    fn check_ciphertext(&self, c: &Mpz) -> Result<()> {
+        if !self.validate_ciphertext(c) {
+            return Err(Error::InvalidCiphertext);
+        }
+        Ok(())
+    }
+
+    /// `Error::PlaintextOverflow` unless 0 <= m < n
+    pub fn check_plaintext(&self, m: &Mpz) -> Result<()> {
+        if *m < Mpz::zero() || *m >= self.n {
+            return Err(Error::PlaintextOverflow);
+        }
+        Ok(())
+    }
+
+    /// Sample r uniformly from the units of Z_n
+    pub fn random_unit(&self, rs: &mut dyn Randomness) -> Mpz {
+        let mut r = rs.urandom(&self.n);
+        while r.gcd(&self.n) != Mpz::one() {
+            r = rs.urandom(&self.n);
+        }
+        r
+    }
+
+    /// Encrypt m in [0, n), `Error::PlaintextOverflow` otherwise
+    pub fn encrypt(&self, rs: &mut dyn Randomness, m: &Mpz) -> Result<Mpz> {
+        self.encrypt_returning_randomness(rs, m).map(|(c, _)| c)
+    }
+
+    /// Encrypt m and also return the nonce r, needed to prove statements
+    /// about the ciphertext
+    pub fn encrypt_returning_randomness(&self, rs: &mut dyn Randomness, m: &Mpz) -> Result<(Mpz, Mpz)> {
+        self.check_plaintext(m)?;
+        let r = self.random_unit(rs);
+        let c = self.encrypt_with_randomness(m, &r)?;
+        Ok((c, r))
+    }
+
+    /// Deterministic (1+n)^m * r^n mod n^2 with a caller-supplied nonce.
+    /// `Error::InvalidRandomness` unless 0 < r < n and gcd(r, n) = 1.
+    pub fn encrypt_with_randomness(&self, m: &Mpz, r: &Mpz) -> Result<Mpz> {
+        self.check_plaintext(m)?;
+        if *r <= Mpz::zero() || *r >= self.n || r.gcd(&self.n) != Mpz::one() {
+            return Err(Error::InvalidRandomness);
+        }
+        Ok(self.encrypt_with_rn(m, &r.powm(&self.n, &self.n2)))
+    }
+
+    /// Encrypt with a precomputed rn = r^n mod n^2
+    pub fn encrypt_with_rn(&self, m: &Mpz, rn: &Mpz) -> Mpz {
+        (self.raw_encrypt(m) * rn) % &self.n2
+    }
+
+    /// Deterministic 1 + m*n mod n^2 without the r^n factor, m is reduced
+    /// mod n. Not semantically secure on its own, it must go through
+    /// `rerandomize` before export.
+    pub fn raw_encrypt(&self, m: &Mpz) -> Mpz {
+        let gm = m.modulus(&self.n) * &self.n + Mpz::one();   // faster version
+        // let gm = self.g.powm(m, &self.n2);
+
+        gm % &self.n2
+    }
+
+    /// Multiply by a fresh r^n mod n^2, the result cannot be linked to c.
+    /// `Error::InvalidCiphertext` if c is not a valid ciphertext.
+    pub fn rerandomize(&self, rs: &mut dyn Randomness, c: &Mpz) -> Result<Mpz> {
+        self.check_ciphertext(c)?;
+        let r = self.random_unit(rs);
+        Ok((c * r.powm(&self.n, &self.n2)) % &self.n2)
+    }
+
+    /// Encrypt a signed integer in [-n/3, n/3]
+    pub fn encrypt_signed(&self, rs: &mut dyn Randomness, m: &Mpz) -> Result<Mpz> {
+        self.encrypt(rs, &encode_signed(self, m)?)
+    }
+
+    /// Encrypt a finite f64 as an `EncryptedNumber`
+    pub fn encrypt_f64(&self, rs: &mut dyn Randomness, x: f64) -> Result<EncryptedNumber> {
+        let e = EncodedNumber::encode_f64(self, x)?;
+        Ok(EncryptedNumber {
+            ciphertext: self.encrypt(rs, &e.encoding)?,
+            exponent: e.exponent,
+        })
+    }
+
+    pub fn add_cipher(&self, c1: &Mpz, c2: &Mpz) -> Result<Mpz> {
+        self.check_ciphertext(c1)?;
+        self.check_ciphertext(c2)?;
+        Ok((c1 * c2) % &self.n2)
+    }
+
+    /// m may be negative, it is reduced mod n
+    pub fn add_const(&self, c: &Mpz, m: &Mpz) -> Result<Mpz> {
+        self.add_cipher(c, &self.raw_encrypt(m))
+    }
+
+    /// m may be negative, it is reduced mod n
+    pub fn mul_const(&self, c: &Mpz, m: &Mpz) -> Result<Mpz> {
+        self.check_ciphertext(c)?;
+        Ok(c.powm(&m.modulus(&self.n), &self.n2))
+    }
+}
+
+impl FastL {
+
+    /// Valid for any u = 1 mod n with (u-1)/n < 2^bit_length(n), n must be odd
+    pub fn new(n: &Mpz) -> Result<FastL> {
+        let mut two_k = Mpz::zero();
+        two_k.setbit(n.bit_length());
+        let ninv = n.invert(&two_k).ok_or(Error::NotInvertible)?;
+        Ok(FastL { ninv, two_k })
+    }
+
+    pub fn apply(&self, u: &Mpz) -> Mpz {
+        ((u - Mpz::one()) * &self.ninv) % &self.two_k
+    }
+}
+
+impl PaiSk {
+
+    /// `Error::NotInvertible` if p and q are not distinct odd primes with
+    /// gcd(pq, (p-1)(q-1)) = 1
+    pub fn new(p: Mpz, q: Mpz, pk: &PaiPk) -> Result<PaiSk> {
+        let lambda  = (&p - Mpz::one()) * (&q - Mpz::one());
+        let mu      = lambda.invert(&pk.n).ok_or(Error::NotInvertible)?;
+        let p2 = &p * &p;
+        let q2 = &q * &q;
+        let lp = FastL::new(&p)?;
+        let lq = FastL::new(&q)?;
+        let hp = PaiSk::h(&pk.g, &p, &p2, &lp)?;
+        let hq = PaiSk::h(&pk.g, &q, &q2, &lq)?;
+        let pinv = p.invert(&q).ok_or(Error::NotInvertible)?;
+        let ln = FastL::new(&pk.n)?;
+        let np = &pk.n % (&p2 - &p);
+        let nq = &pk.n % (&q2 - &q);
+        let p2inv = p2.invert(&q2).ok_or(Error::NotInvertible)?;
+
+        Ok(PaiSk { lambda, mu, p, q, p2, q2, hp, hq, pinv, ln, lp, lq, np, nq, p2inv })
+    }
+
+    /// Owner-side encryption, computes r^n mod n^2 through CRT over p^2 and q^2
+    pub fn encrypt(&self, pk: &PaiPk, rs: &mut dyn Randomness, m: &Mpz) -> Result<Mpz> {
+        pk.check_plaintext(m)?;
+        let r = pk.random_unit(rs);
+        let rp = (&r % &self.p2).powm(&self.np, &self.p2);
+        let rq = (&r % &self.q2).powm(&self.nq, &self.q2);
+        let u = ((rq - &rp) * &self.p2inv).modulus(&self.q2);
+        let rn = rp + u * &self.p2;
+        let gm = m * &pk.n + Mpz::one();
+
+        Ok((&gm * &rn) % &pk.n2)
+    }
+
+    /// hp = L_p(g^(p-1) mod p^2)^-1 mod p
+    fn h(g: &Mpz, p: &Mpz, p2: &Mpz, lp: &FastL) -> Result<Mpz> {
+        let pm1 = p - Mpz::one();
+        let gp = g.powm(&pm1, p2);
+        lp.apply(&gp).invert(p).ok_or(Error::NotInvertible)
+    }
+
+    /// 0 < c < n^2 and gcd(c, n) = 1, checked with the factors of n
+    pub fn validate_ciphertext(&self, c: &Mpz) -> bool {
+        *c > Mpz::zero() && *c < &self.p2 * &self.q2
+            && !(c % &self.p).is_zero() && !(c % &self.q).is_zero()
+    }
+
+    /// Decrypt modulo p^2 and q^2 separately and recombine with CRT
+    pub fn decrypt_crt(&self, c: &Mpz) -> Result<Mpz> {
+        if !self.validate_ciphertext(c) {
+            return Err(Error::InvalidCiphertext);
+        }
+        let mp = PaiSk::decrypt_part(c, &self.p, &self.p2, &self.hp, &self.lp);
+        let mq = PaiSk::decrypt_part(c, &self.q, &self.q2, &self.hq, &self.lq);
+        let u = ((mq - &mp) * &self.pinv).modulus(&self.q);
+        Ok(mp + u * &self.p)
+    }
+
+    fn decrypt_part(c: &Mpz, p: &Mpz, p2: &Mpz, hp: &Mpz, lp: &FastL) -> Mpz {
+        let pm1 = p - Mpz::one();
+        let cp = (c % p2).powm(&pm1, p2);
+        (lp.apply(&cp) * hp) % p
+    }
+
+    pub fn decrypt(&self, pk: &PaiPk, c: &Mpz) -> Result<Mpz> {
+        pk.check_ciphertext(c)?;
+        let cl = c.powm(&self.lambda, &pk.n2);
+        let lc = self.ln.apply(&cl);
+        Ok((&lc * &self.mu) % &pk.n)
+    }
+
+    /// Open c = g^m * r^n mod n^2 into (m, r), with
+    /// r = (c * g^-m)^(n^-1 mod lambda) mod n
+    pub fn decrypt_with_randomness(&self, pk: &PaiPk, c: &Mpz) -> Result<(Mpz, Mpz)> {
+        let m = self.decrypt_crt(c)?;
+        let rn = (c * pk.raw_encrypt(&(&pk.n - &m))) % &pk.n2;
+        let d = pk.n.invert(&self.lambda).ok_or(Error::NotInvertible)?;
+        let r = (rn % &pk.n).powm(&d, &pk.n);
+        Ok((m, r))
+    }
+
+    /// Decrypt a signed integer, `Error::PlaintextOverflow` if the plaintext
+    /// lies in the overflow band
+    pub fn decrypt_signed(&self, pk: &PaiPk, c: &Mpz) -> Result<Mpz> {
+        decode_signed(pk, &self.decrypt_crt(c)?)
+    }
+
+    pub fn decrypt_f64(&self, pk: &PaiPk, c: &EncryptedNumber) -> Result<f64> {
+        let e = EncodedNumber { encoding: self.decrypt_crt(&c.ciphertext)?, exponent: c.exponent };
+        e.decode_f64(pk)
+    }
 }
 
 impl Paillier {
 
-    pub fn new(keysize: usize) -> Paillier {
-        let mut rng = rand::thread_rng();
-        let mut randstate = RandState::new();
-        randstate.seed_ui( rng.gen::<u64>() );
-        let (pk, sk) = Paillier::generate_key(&mut randstate, keysize);
+    /// `Error::InvalidKeySize` unless keysize is even and at least `MIN_KEY_SIZE`
+    pub fn new(keysize: usize) -> Result<Paillier> {
+        Paillier::with_rng(keysize, Box::new(RandomSource::new()?))
+    }
+
+    /// Generate the key with `rs` and keep it for later encryptions
+    pub fn with_rng(keysize: usize, mut rs: Box<dyn Randomness>) -> Result<Paillier> {
+        let (pk, sk) = Paillier::generate_key(&mut *rs, keysize)?;
 
-        Paillier { pk: pk, sk: sk, rs: randstate }
+        Ok(Paillier { pk: pk, sk: sk, rs: rs })
     }
 
-    fn generate_key(mut randstate: &mut RandState, keysize: usize) -> (PaiPk, PaiSk) {
-        assert!(keysize % 2 == 0);
+    /// Derive the key, and every later encryption nonce, from `seed` through
+    /// a `HashDrbg`. The same seed always gives the same key and ciphertexts.
+    /// Anyone who knows the seed knows the key: for test fixtures only.
+    pub fn from_seed(keysize: usize, seed: &[u8]) -> Result<Paillier> {
+        Paillier::with_rng(keysize, Box::new(HashDrbg::new(seed)))
+    }
+
+    fn generate_key(mut randstate: &mut dyn Randomness, keysize: usize) -> Result<(PaiPk, PaiSk)> {
+        check_key_size(keysize)?;
+
+        // Blum primes so that the modulus admits a Paillier-Blum proof
+        let p = generate_blum_prime(&mut randstate, keysize/2);
+        let mut q = generate_blum_prime(&mut randstate, keysize/2);
+        while p == q || (&p * &q).bit_length() != keysize {
+            q = generate_blum_prime(&mut randstate, keysize/2);
+        }
 
-        let p = generate_strong_prime(&mut randstate, keysize/2);
-        let q = generate_strong_prime(&mut randstate, keysize/2);
-        
         let n           = &p * &q;
         let g       = &n + Mpz::one();
-        let lambda  = (&p - Mpz::one()) * (&q - Mpz::one());
-        let mu      = lambda.invert(&n).unwrap();
         let n2 = &n * &n;
+        let pk = PaiPk {n: n, n2: n2, g: g};
+        let sk = PaiSk::new(p, q, &pk)?;
+
+        Ok((pk, sk))
+    }
+
+    /// Split into a public-key-only encryptor and a decryptor
+    pub fn split(self) -> (PaiEncryptor, PaiDecryptor) {
+        let enc = PaiEncryptor { pk: self.pk.clone(), rs: self.rs };
+        let dec = PaiDecryptor { pk: self.pk, sk: self.sk };
+        (enc, dec)
+    }
+
+    pub fn encryptor(&self) -> Result<PaiEncryptor> {
+        PaiEncryptor::new(self.pk.clone())
+    }
+
+    pub fn encrypt(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt(&mut self.rs, m)
+    }
+
+    pub fn encrypt_with_randomness(&self, m: &Mpz, r: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt_with_randomness(m, r)
+    }
+
+    pub fn encrypt_returning_randomness(&mut self, m: &Mpz) -> Result<(Mpz, Mpz)> {
+        self.pk.encrypt_returning_randomness(&mut self.rs, m)
+    }
+
+    /// Encrypt using the secret factorization, much faster than `encrypt`
+    pub fn encrypt_owner(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.sk.encrypt(&self.pk, &mut self.rs, m)
+    }
+
+    pub fn encrypt_signed(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt_signed(&mut self.rs, m)
+    }
+
+    pub fn encrypt_f64(&mut self, x: f64) -> Result<EncryptedNumber> {
+        self.pk.encrypt_f64(&mut self.rs, x)
+    }
+
+    pub fn decrypt(&mut self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt(&self.pk, c)
+    }
+
+    /// Plaintext and nonce of c, to open it to a third party
+    pub fn decrypt_with_randomness(&self, c: &Mpz) -> Result<(Mpz, Mpz)> {
+        self.sk.decrypt_with_randomness(&self.pk, c)
+    }
+
+    pub fn decrypt_signed(&self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt_signed(&self.pk, c)
+    }
+
+    pub fn decrypt_f64(&self, c: &EncryptedNumber) -> Result<f64> {
+        self.sk.decrypt_f64(&self.pk, c)
+    }
 
-        (PaiPk {n: n, n2: n2, g: g}, PaiSk {lambda: lambda, mu: mu} )
+    pub fn decrypt_crt(&mut self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt_crt(c)
     }
 
-    pub fn encrypt(&mut self, m: &Mpz) -> Mpz {
-        let mut r = self.rs.urandom(&self.pk.n);
-        while r.gcd(&self.pk.n) != Mpz::one() {
-            r = self.rs.urandom(&self.pk.n);
+    /// `Error::KeyMismatch` if c was encrypted under another key
+    pub fn decrypt_ciphertext(&self, c: &Ciphertext) -> Result<Mpz> {
+        if !c.same_key(&self.pk) {
+            return Err(Error::KeyMismatch);
         }
+        self.sk.decrypt_crt(&c.c)
+    }
+
+    pub fn rerandomize(&mut self, c: &Mpz) -> Result<Mpz> {
+        self.pk.rerandomize(&mut self.rs, c)
+    }
+
+    pub fn add_cipher(&self, c1: &Mpz, c2: &Mpz) -> Result<Mpz> {
+        self.pk.add_cipher(c1, c2)
+    }
+
+    pub fn add_const(&self, c: &Mpz, m: &Mpz) -> Result<Mpz> {
+        self.pk.add_const(c, m)
+    }
+
+    pub fn mul_const(&self, c: &Mpz, m: &Mpz) -> Result<Mpz> {
+        self.pk.mul_const(c, m)
+    }
+}
+
+impl PaiEncryptor {
+
+    pub fn new(pk: PaiPk) -> Result<PaiEncryptor> {
+        Ok(PaiEncryptor::with_rng(pk, Box::new(RandomSource::new()?)))
+    }
+
+    pub fn with_rng(pk: PaiPk, rs: Box<dyn Randomness>) -> PaiEncryptor {
+        PaiEncryptor { pk, rs }
+    }
+
+    pub fn encrypt(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt(&mut self.rs, m)
+    }
+
+    pub fn encrypt_with_randomness(&self, m: &Mpz, r: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt_with_randomness(m, r)
+    }
 
-        let rn = r.powm(&self.pk.n, &self.pk.n2);
-        let gm = m * &self.pk.n + Mpz::one();   // faster version
-        // let gm = self.pk.g.powm(m, &self.pk.n2);
+    pub fn encrypt_returning_randomness(&mut self, m: &Mpz) -> Result<(Mpz, Mpz)> {
+        self.pk.encrypt_returning_randomness(&mut self.rs, m)
+    }
+
+    pub fn encrypt_signed(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt_signed(&mut self.rs, m)
+    }
+
+    pub fn encrypt_f64(&mut self, x: f64) -> Result<EncryptedNumber> {
+        self.pk.encrypt_f64(&mut self.rs, x)
+    }
+
+    pub fn rerandomize(&mut self, c: &Mpz) -> Result<Mpz> {
+        self.pk.rerandomize(&mut self.rs, c)
+    }
+
+    pub fn add_cipher(&self, c1: &Mpz, c2: &Mpz) -> Result<Mpz> {
+        self.pk.add_cipher(c1, c2)
+    }
+
+    pub fn add_const(&self, c: &Mpz, m: &Mpz) -> Result<Mpz> {
+        self.pk.add_const(c, m)
+    }
+
+    pub fn mul_const(&self, c: &Mpz, m: &Mpz) -> Result<Mpz> {
+        self.pk.mul_const(c, m)
+    }
+}
+
+impl PaiDecryptor {
 
-        (&gm*&rn ) % &self.pk.n2
+    pub fn new(pk: PaiPk, sk: PaiSk) -> PaiDecryptor {
+        PaiDecryptor { pk, sk }
     }
 
-    pub fn decrypt(&mut self, c: &Mpz) -> Mpz {
-        let cl = c.powm(&self.sk.lambda, &self.pk.n2);
-        let lc = (cl - Mpz::one()) / &self.pk.n;
-        (&lc * &self.sk.mu) % &self.pk.n
+    pub fn decrypt(&self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt(&self.pk, c)
     }
 
-    pub fn add_cipher(&self, c1: &Mpz, c2: &Mpz) -> Mpz {
-        (c1 * c2) % &self.pk.n2
+    pub fn decrypt_with_randomness(&self, c: &Mpz) -> Result<(Mpz, Mpz)> {
+        self.sk.decrypt_with_randomness(&self.pk, c)
     }
 
-    pub fn add_const(&self, c: &Mpz, m: &Mpz) -> Mpz {
-        self.add_cipher(c, &self.pk.g.powm(&m, &self.pk.n2))
+    pub fn decrypt_signed(&self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt_signed(&self.pk, c)
     }
 
-    pub fn mul_const(&self, c: &Mpz, m: &Mpz) -> Mpz {
-       c.powm(&m, &self.pk.n2) 
+    pub fn decrypt_f64(&self, c: &EncryptedNumber) -> Result<f64> {
+        self.sk.decrypt_f64(&self.pk, c)
+    }
+
+    pub fn decrypt_crt(&self, c: &Mpz) -> Result<Mpz> {
+        self.sk.decrypt_crt(c)
+    }
+
+    /// `Error::KeyMismatch` if c was encrypted under another key
+    pub fn decrypt_ciphertext(&self, c: &Ciphertext) -> Result<Mpz> {
+        if !c.same_key(&self.pk) {
+            return Err(Error::KeyMismatch);
+        }
+        self.sk.decrypt_crt(&c.c)
     }
 }
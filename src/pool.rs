@@ -0,0 +1,156 @@
+//! Precomputed randomness for Paillier encryption
+//!
+//! The expensive part of an encryption is r^n mod n^2, which does not depend
+//! on the message. A `RandPool` computes these values ahead of time, either
+//! on demand with `fill` or in a background thread with `fill_background`,
+//! so that `encrypt` costs a single modular multiplication.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::thread;
+use gmp::mpz::Mpz;
+use paillier::PaiPk;
+use rand::OsRng;
+use rng::{Randomness, RngSource};
+use error::{Error, Result};
+
+/// What `RandPool::encrypt` does when no precomputed value is left
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DryPolicy {
+    /// Compute a fresh r^n on the spot, encryption is as slow as `PaiPk::encrypt`
+    Compute,
+    /// Wait for the background producer, fail if none is running
+    Block,
+    /// Fail immediately, `encrypt` returns `Error::PoolEmpty`
+    Fail,
+}
+
+pub struct RandPool {
+    pub pk     : PaiPk,
+    pub policy : DryPolicy,
+    // shared with the background producer
+    rs         : Arc<Mutex<Box<dyn Randomness + Send>>>,
+    values     : VecDeque<Mpz>,
+    background : Option<Receiver<Mpz>>,
+}
+
+impl RandPool {
+
+    /// Pool drawing from the operating system CSPRNG, `Error::Entropy` if it is unavailable
+    pub fn new(pk: PaiPk, policy: DryPolicy) -> Result<RandPool> {
+        let os = OsRng::new().map_err(|_| Error::Entropy)?;
+        Ok(RandPool::with_rng(pk, policy, Box::new(RngSource(os))))
+    }
+
+    /// Pool drawing from `rs`, in the calling thread and in the background producer alike
+    pub fn with_rng(pk: PaiPk, policy: DryPolicy, rs: Box<dyn Randomness + Send>) -> RandPool {
+        RandPool { pk, policy, rs: Arc::new(Mutex::new(rs)), values: VecDeque::new(), background: None }
+    }
+
+    /// Number of values ready to use, not counting those still in the background
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Precompute `count` values in the calling thread
+    pub fn fill(&mut self, count: usize) {
+        for _ in 0..count {
+            let rn = RandPool::generate(&self.pk, &self.rs);
+            self.values.push_back(rn);
+        }
+    }
+
+    /// Precompute `count` values in a background thread. Values are picked up
+    /// as they are produced. `Error::PoolBusy` while a previous producer is
+    /// still running, so its values are never lost.
+    pub fn fill_background(&mut self, count: usize) -> Result<()> {
+        self.collect();
+        if self.background.is_some() {
+            return Err(Error::PoolBusy);
+        }
+        let (tx, rx) = channel();
+        let pk = self.pk.clone();
+        let rs = self.rs.clone();
+
+        thread::spawn(move || {
+            for _ in 0..count {
+                if tx.send(RandPool::generate(&pk, &rs)).is_err() {
+                    break;
+                }
+            }
+        });
+        self.background = Some(rx);
+        Ok(())
+    }
+
+    /// Move every value the background producer has finished into the pool
+    pub fn collect(&mut self) {
+        let mut done = false;
+        if let Some(ref rx) = self.background {
+            loop {
+                match rx.try_recv() {
+                    Ok(rn) => self.values.push_back(rn),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => { done = true; break; }
+                }
+            }
+        }
+        if done {
+            self.background = None;
+        }
+    }
+
+    /// Take one r^n mod n^2, following `policy` if the pool is dry
+    pub fn take(&mut self) -> Option<Mpz> {
+        self.collect();
+        if let Some(rn) = self.values.pop_front() {
+            return Some(rn);
+        }
+
+        match self.policy {
+            DryPolicy::Compute => Some(RandPool::generate(&self.pk, &self.rs)),
+            DryPolicy::Block => {
+                let rn = match self.background {
+                    Some(ref rx) => rx.recv().ok(),
+                    None => None,
+                };
+                if rn.is_none() {
+                    self.background = None;
+                }
+                rn
+            }
+            DryPolicy::Fail => None,
+        }
+    }
+
+    /// Encrypt m in [0, n), `Error::PoolEmpty` if no value can be taken
+    pub fn encrypt(&mut self, m: &Mpz) -> Result<Mpz> {
+        self.pk.check_plaintext(m)?;
+        let rn = self.take().ok_or(Error::PoolEmpty)?;
+        Ok(self.pk.encrypt_with_rn(m, &rn))
+    }
+
+    /// Rerandomize c with a value from the pool, `Error::InvalidCiphertext`
+    /// if c is not a valid ciphertext
+    pub fn rerandomize(&mut self, c: &Mpz) -> Result<Mpz> {
+        if !self.pk.validate_ciphertext(c) {
+            return Err(Error::InvalidCiphertext);
+        }
+        let rn = self.take().ok_or(Error::PoolEmpty)?;
+        Ok((c * rn) % &self.pk.n2)
+    }
+
+    // the lock is only held while drawing r, not for the exponentiation
+    fn generate(pk: &PaiPk, rs: &Mutex<Box<dyn Randomness + Send>>) -> Mpz {
+        let r = {
+            let mut rs = rs.lock().unwrap_or_else(|e| e.into_inner());
+            pk.random_unit(&mut *rs)
+        };
+        r.powm(&pk.n, &pk.n2)
+    }
+}
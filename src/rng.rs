@@ -1,7 +1,161 @@
+use std::sync::OnceLock;
 use gmp::mpz::Mpz;
 use gmp::rand::RandState;
+use rand::{OsRng, Rng};
+use sha2::{Digest, Sha256};
+use error::{Error, Result};
+use zk::modulus::SMALL_PRIME_BOUND;
 
-pub fn generate_urandom(randstate: &mut RandState, len: usize) -> Mpz {
+/// Source of uniformly random integers for primes, encryption nonces and
+/// proof masks. Key generation and encryption take any implementation, so a
+/// hardware source or a seeded generator can be plugged in.
+pub trait Randomness {
+    /// Uniform in [0, 2^bits)
+    fn urandom_2exp(&mut self, bits: u64) -> Mpz;
+
+    /// Uniform in [0, n) by rejection sampling
+    ///
+    /// # Panics
+    ///
+    /// If n is not positive. The checked key constructors reject such moduli.
+    fn urandom(&mut self, n: &Mpz) -> Mpz {
+        assert!(*n > Mpz::zero(), "urandom bound must be positive");
+        let bits = n.bit_length() as u64;
+        loop {
+            let x = self.urandom_2exp(bits);
+            if x < *n {
+                return x;
+            }
+        }
+    }
+}
+
+impl<R: Randomness + ?Sized> Randomness for Box<R> {
+    fn urandom_2exp(&mut self, bits: u64) -> Mpz {
+        (**self).urandom_2exp(bits)
+    }
+
+    fn urandom(&mut self, n: &Mpz) -> Mpz {
+        (**self).urandom(n)
+    }
+}
+
+impl<R: Randomness + ?Sized> Randomness for &mut R {
+    fn urandom_2exp(&mut self, bits: u64) -> Mpz {
+        (**self).urandom_2exp(bits)
+    }
+
+    fn urandom(&mut self, n: &Mpz) -> Mpz {
+        (**self).urandom(n)
+    }
+}
+
+/// Big-endian bytes from `fill`, truncated to `bits` bits
+fn urandom_bytes<F: FnOnce(&mut [u8])>(bits: u64, fill: F) -> Mpz {
+    if bits == 0 {
+        return Mpz::zero();
+    }
+    let mut bytes = vec![0u8; (bits as usize).div_ceil(8)];
+    fill(&mut bytes);
+    Mpz::from(&bytes[..]) >> (8 * bytes.len() - bits as usize)
+}
+
+/// Adapter for any `rand` generator. A newtype rather than a blanket impl,
+/// which would overlap with the one for `Box`.
+pub struct RngSource<R: Rng>(pub R);
+
+impl<R: Rng> Randomness for RngSource<R> {
+    fn urandom_2exp(&mut self, bits: u64) -> Mpz {
+        let rng = &mut self.0;
+        urandom_bytes(bits, |bytes| rng.fill_bytes(bytes))
+    }
+}
+
+const DRBG_TAG: &[u8] = b"gm_paillier hash drbg";
+
+/// Deterministic generator: SHA-256 in counter mode under a key derived from
+/// the seed. The same seed always gives the same keys and ciphertexts, which
+/// is what tests need. For deterministic fixtures only: the key never
+/// changes, so whoever learns the state can recompute all earlier output.
+#[derive(Clone)]
+pub struct HashDrbg {
+    key     : [u8; 32],
+    counter : u64,
+}
+
+impl HashDrbg {
+
+    pub fn new(seed: &[u8]) -> HashDrbg {
+        let mut h = Sha256::new();
+        h.update(DRBG_TAG);
+        h.update(seed);
+        HashDrbg { key: h.finalize().into(), counter: 0 }
+    }
+
+    pub fn fill_bytes(&mut self, out: &mut [u8]) {
+        for chunk in out.chunks_mut(32) {
+            let mut h = Sha256::new();
+            h.update(self.key);
+            h.update(self.counter.to_be_bytes());
+            self.counter += 1;
+            let block = h.finalize();
+            chunk.copy_from_slice(&block[..chunk.len()]);
+        }
+    }
+}
+
+impl Randomness for HashDrbg {
+    fn urandom_2exp(&mut self, bits: u64) -> Mpz {
+        urandom_bytes(bits, |bytes| self.fill_bytes(bytes))
+    }
+}
+
+/// Default source of the library: the operating system CSPRNG, or GMP's
+/// Mersenne Twister when explicitly asked for
+pub enum RandomSource {
+    /// Operating system CSPRNG, used by every constructor of the library
+    Os(OsRng),
+    /// GMP's Mersenne Twister. NOT cryptographically secure, its future output
+    /// can be predicted from a few observed values. Only for tests and
+    /// benchmarks that need reproducible randomness.
+    /// This is the adapter for a `RandState`, which has no `Randomness` impl
+    /// of its own. It is not `Send`, so it cannot back a `RandPool`.
+    InsecureMt(RandState),
+}
+
+impl RandomSource {
+
+    /// Read from the operating system CSPRNG, `Error::Entropy` if it is unavailable
+    pub fn new() -> Result<RandomSource> {
+        OsRng::new().map(RandomSource::Os).map_err(|_| Error::Entropy)
+    }
+
+    /// Mersenne Twister seeded with `seed`. Insecure, see `InsecureMt`.
+    pub fn insecure_mt(seed: u64) -> RandomSource {
+        let mut rs = RandState::new();
+        rs.seed_ui(seed);
+        RandomSource::InsecureMt(rs)
+    }
+}
+
+impl Randomness for RandomSource {
+    fn urandom_2exp(&mut self, bits: u64) -> Mpz {
+        match *self {
+            RandomSource::Os(ref mut rng) => urandom_bytes(bits, |bytes| rng.fill_bytes(bytes)),
+            RandomSource::InsecureMt(ref mut rs) => rs.urandom_2exp(bits),
+        }
+    }
+
+    fn urandom(&mut self, n: &Mpz) -> Mpz {
+        assert!(*n > Mpz::zero(), "urandom bound must be positive");
+        match *self {
+            RandomSource::Os(ref mut rng) => RngSource(rng).urandom(n),
+            RandomSource::InsecureMt(ref mut rs) => rs.urandom(n),
+        }
+    }
+}
+
+pub fn generate_urandom(randstate: &mut dyn Randomness, len: usize) -> Mpz {
 
     let mut a : Mpz = randstate.urandom_2exp(len as u64 - 1);
     a.setbit(len - 1);
@@ -9,7 +163,7 @@ pub fn generate_urandom(randstate: &mut RandState, len: usize) -> Mpz {
     a
 }
 
-pub fn generate_prime(randstate: &mut RandState, len: usize) -> Mpz {
+pub fn generate_prime(randstate: &mut dyn Randomness, len: usize) -> Mpz {
     loop {
         let a: Mpz = generate_urandom(randstate, len);
         let p: Mpz = a.nextprime();
@@ -17,7 +171,7 @@ pub fn generate_prime(randstate: &mut RandState, len: usize) -> Mpz {
     }
 }
 /// Generate a prime p such that p-1 has a large prime factor
-pub fn generate_strong_prime(mut randstate: &mut RandState, len: usize) -> Mpz {
+pub fn generate_strong_prime(mut randstate: &mut dyn Randomness, len: usize) -> Mpz {
 
     // generate a half-size prime pp
     let pp = generate_prime(randstate, len / 2);
@@ -36,3 +190,93 @@ pub fn generate_strong_prime(mut randstate: &mut RandState, len: usize) -> Mpz {
         else { p = &p + &a; }
     }
 }
+
+/// Odd primes below `SMALL_PRIME_BOUND`, sieved once and shared by safe
+/// prime generation and `has_small_factor`
+pub(crate) fn small_odd_primes() -> &'static [u64] {
+    static PRIMES: OnceLock<Vec<u64>> = OnceLock::new();
+    PRIMES.get_or_init(|| {
+        let bound = SMALL_PRIME_BOUND as usize;
+        let mut sieve = vec![true; bound];
+        let mut primes = Vec::new();
+        for p in 2..bound {
+            if !sieve[p] {
+                continue;
+            }
+            if p > 2 {
+                primes.push(p as u64);
+            }
+            let mut k = p * p;
+            while k < bound {
+                sieve[k] = false;
+                k += p;
+            }
+        }
+        primes
+    })
+}
+
+/// 2^(n-1) = 1 mod n, a cheap filter before the full primality test
+fn fermat2(n: &Mpz) -> bool {
+    Mpz::from(2u64).powm(&(n - Mpz::one()), n) == Mpz::one()
+}
+
+/// Generate a safe prime p = 2p' + 1 with p' prime
+///
+/// Walks p' through odd numbers from a random start and skips every
+/// candidate where p' or 2p' + 1 has a small prime factor, then filters with
+/// a base 2 Fermat test before the full primality tests. A 1024-bit safe
+/// prime takes a few seconds, so 2048-bit threshold keys are practical while
+/// much larger ones are not.
+pub fn generate_safe_prime(randstate: &mut dyn Randomness, len: usize) -> Mpz {
+    // p' >= 2^(len-2), so a small prime never equals p' or 2p' + 1
+    let all = small_odd_primes();
+    let primes = &all[..all.partition_point(|&r| len - 2 >= 16 || r < 1 << (len - 2))];
+    loop {
+        let mut pp = generate_urandom(randstate, len - 1);
+        pp.setbit(0);
+        // residues of p' modulo the small primes, updated as p' steps by 2
+        let mut rems: Vec<u64> = primes.iter().map(|&r| {
+            let rem: Option<u64> = (&(&pp % r)).into();
+            rem.unwrap_or(0)
+        }).collect();
+
+        while pp.bit_length() == len - 1 {
+            // r divides p' when p' = 0 mod r, and 2p' + 1 when p' = (r-1)/2 mod r
+            let sieved = primes.iter().zip(rems.iter()).all(|(&r, &rem)| {
+                rem != 0 && rem != (r - 1) / 2
+            });
+            if sieved && fermat2(&pp) {
+                let p: Mpz = &pp * 2u64 + Mpz::one();
+                if fermat2(&p) && pp.probab_prime_p(40) && p.probab_prime_p(40) {
+                    return p;
+                }
+            }
+            pp = pp + 2u64;
+            for (rem, &r) in rems.iter_mut().zip(primes.iter()) {
+                *rem = (*rem + 2) % r;
+            }
+        }
+    }
+}
+
+/// Generate a strong prime p with p = 3 mod 4, so that n = pq is a Blum integer
+pub fn generate_blum_prime(randstate: &mut dyn Randomness, len: usize) -> Mpz {
+    loop {
+        let p = generate_strong_prime(randstate, len);
+        if &p % 4u64 == Mpz::from(3u64) {
+            return p;
+        }
+    }
+}
+
+/// Smallest modulus size in bits accepted by key generation
+pub const MIN_KEY_SIZE: usize = 128;
+
+/// Key sizes must be even and at least `MIN_KEY_SIZE`
+pub fn check_key_size(keysize: usize) -> Result<()> {
+    if !keysize.is_multiple_of(2) || keysize < MIN_KEY_SIZE {
+        return Err(Error::InvalidKeySize);
+    }
+    Ok(())
+}
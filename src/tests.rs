@@ -1,142 +1,918 @@
 
 use super::rng::generate_strong_prime;
 use super::rng::generate_urandom;
-use super::gm::GM;
+use super::rng::generate_safe_prime;
+use super::rng::{RandomSource, Randomness, RngSource, HashDrbg};
+use super::gm::{GM, GmPk};
+use super::error::Error;
 use super::gmp::mpz::Mpz;
-use super::paillier::Paillier;
-use super::gmp::rand::RandState;
-use super::rand::Rng;
+use super::paillier::{Paillier, PaiEncryptor, PaiPk};
+use super::pool::{RandPool, DryPolicy};
+use super::ciphertext::Ciphertext;
+use super::damgard_jurik::{DamgardJurik, DjPk};
+use super::threshold::ThresholdPk;
+use super::zk::affine::AffineProof;
+use super::zk::membership::{MembershipProof, ZeroOneProof};
+use super::zk::modulus::{ModulusProof, SquareFreeProof};
+use super::zk::plaintext::PlaintextProof;
+use super::zk::range::RangeProof;
+use super::packing::Packer;
+use super::encoding::{encode_signed, decode_signed, max_signed, EncodedNumber, EncryptedNumber};
 use super::rand;
 use test::Bencher;
 
 #[test]
 fn strong_prime() {
-    let mut rng = rand::thread_rng();
-    let mut randstate = RandState::new();
-    randstate.seed_ui( rng.gen::<u64>() );
+    let mut randstate = RandomSource::new().unwrap();
     let p = generate_strong_prime(&mut randstate, 1024);
     assert!( p.probab_prime_p(40) == true);
 }
 
+#[test]
+fn random_source() {
+    let mut os = RandomSource::new().unwrap();
+    let n = Mpz::from(1000u64);
+    let mut seen_high = false;
+    for _ in 0..200 {
+        let x = os.urandom(&n);
+        assert!( x >= Mpz::zero() && x < n );
+        seen_high |= x >= Mpz::from(500u64);
+        assert!( os.urandom_2exp(13).bit_length() <= 13 );
+    }
+    assert!( seen_high );
+    assert!( os.urandom_2exp(0) == Mpz::zero() );
+    assert!( os.urandom_2exp(256) != os.urandom_2exp(256) );
+
+    let mut mt1 = RandomSource::insecure_mt(5);
+    let mut mt2 = RandomSource::insecure_mt(5);
+    assert!( mt1.urandom(&n) == mt2.urandom(&n) );
+}
+
+#[test]
+fn pluggable_randomness() {
+    let n = Mpz::from(1u64 << 40);
+    let mut sources: Vec<Box<dyn Randomness>> = vec![
+        Box::new(RngSource(rand::XorShiftRng::new_unseeded())),
+        Box::new(RngSource(rand::OsRng::new().unwrap())),
+        Box::new(HashDrbg::new(b"seed")),
+        Box::new(RandomSource::insecure_mt(9)),
+    ];
+    for rs in sources.iter_mut() {
+        let x = rs.urandom(&n);
+        assert!( x < n );
+        assert!( rs.urandom_2exp(100).bit_length() <= 100 );
+        assert!( rs.urandom_2exp(100) != rs.urandom_2exp(100) );
+    }
+
+    // same seed, same key and same ciphertexts
+    let mut a = Paillier::with_rng(512, Box::new(HashDrbg::new(b"fixed"))).unwrap();
+    let mut b = Paillier::with_rng(512, Box::new(HashDrbg::new(b"fixed"))).unwrap();
+    let c = Paillier::with_rng(512, Box::new(HashDrbg::new(b"other"))).unwrap();
+    assert!( a.pk.n == b.pk.n && a.sk.p == b.sk.p );
+    assert!( a.pk.n != c.pk.n );
+    let m = Mpz::from(42u64);
+    let ca = a.encrypt(&m).unwrap();
+    assert!( ca == b.encrypt(&m).unwrap() );
+    assert!( a.decrypt(&ca).unwrap() == m );
+
+    let mut drbg = HashDrbg::new(b"pk side");
+    let c1 = a.pk.encrypt(&mut drbg, &m).unwrap();
+    let c2 = a.pk.encrypt(&mut HashDrbg::new(b"pk side"), &m).unwrap();
+    assert!( c1 == c2 );
+
+    let mut gm = GM::with_rng(512, Box::new(RngSource(rand::OsRng::new().unwrap()))).unwrap();
+    let cg = gm.encrypt(true);
+    assert!( gm.decrypt(&cg).unwrap() );
+
+    // the background producer draws from the pool's own source
+    let mut pool = RandPool::with_rng(a.pk.clone(), DryPolicy::Block, Box::new(HashDrbg::new(b"pool")));
+    pool.fill_background(2).unwrap();
+    let cp = pool.encrypt(&m).unwrap();
+    assert!( a.decrypt(&cp).unwrap() == m );
+    let mut twin = RandPool::with_rng(a.pk.clone(), DryPolicy::Fail, Box::new(HashDrbg::new(b"pool")));
+    twin.fill(2);
+    assert!( twin.encrypt(&m).unwrap() == cp );
+    assert!( twin.encrypt(&m).unwrap() == pool.encrypt(&m).unwrap() );
+}
+
+#[test]
+fn keys_from_seed() {
+    // pinned so that a change in the derivation is noticed
+    let n_pai = "9bee1d6ed2b3613ba88d97c86399fa0435998d64ba7702818eddf4d42047632f\
+                 887a8ea279ca1182d911d5380b5d6ac317b5cbf72a5bebdacd29c1da2159c9d1";
+    let n_gm = "1050f45dceeba0d61da1e4840d9cdc1ab76cb8054c21fac7f78b6c9f5a635e009\
+                84a84c41a92c5053cdac3e9d3be789379c8152c92c1cda72ddec52bf13db374b";
+    let c_42 = "294938e7e86aef5afb8988b27762d17d02f557964fb085a01765d1db6d01c3f6\
+                0176a8833fca66235c2c7706eec2bec7a98d2965c4edd9ee4167cb62cbe0b7c3\
+                bea4350b2e0febd831a31f7a462b0783702b208927cf27ebc57594f1881ed581\
+                7e65c609559b10745daaea3fc1f0b1f47be0243b4907b3dee3810bb650b3baf8";
+
+    let mut paics = Paillier::from_seed(512, b"gm_paillier test vector").unwrap();
+    assert!( paics.pk.n.to_str_radix(16) == n_pai );
+    let c = paics.encrypt(&Mpz::from(42u64)).unwrap();
+    assert!( c.to_str_radix(16) == c_42 );
+    assert!( paics.decrypt(&c).unwrap() == Mpz::from(42u64) );
+
+    let mut gmcrypto = GM::from_seed(512, b"gm_paillier test vector").unwrap();
+    assert!( gmcrypto.pk.n.to_str_radix(16) == n_gm );
+    let c = gmcrypto.encrypt(true);
+    assert!( gmcrypto.decrypt(&c).unwrap() );
+
+    let other = Paillier::from_seed(512, b"gm_paillier test vector 2").unwrap();
+    assert!( other.pk.n != paics.pk.n );
+}
+
+#[test]
+fn safe_prime() {
+    let mut randstate = RandomSource::insecure_mt(42);
+    let p = generate_safe_prime(&mut randstate, 256);
+    assert!( p.bit_length() == 256 );
+    assert!( p.probab_prime_p(40) );
+    assert!( ((&p - Mpz::one()) / 2u64).probab_prime_p(40) );
+}
+
 #[test]
 fn gm_gen_key() {
-    let gmcrypto = GM::new(1024);
+    let gmcrypto = GM::new(1024).unwrap();
     assert!( &gmcrypto.sk.p != &gmcrypto.sk.q );
 }
 
 #[test]
 fn gm_enc_dec() {
-    let mut gmcrypto = GM::new(1024);
+    let mut gmcrypto = GM::new(1024).unwrap();
     let c = gmcrypto.encrypt(true);
-    assert!( gmcrypto.decrypt(&c) == true);
+    assert!( gmcrypto.decrypt(&c).unwrap() );
     let c1 = gmcrypto.encrypt(true);
     assert!( &c1 != &c );
-    assert!( gmcrypto.decrypt(&c1) == true);
+    assert!( gmcrypto.decrypt(&c1).unwrap() );
     let c2 = gmcrypto.encrypt(false);
     let c3 = gmcrypto.encrypt(false);
     assert!( &c2 != &c3 );
-    assert!( gmcrypto.decrypt(&c2) == false);
-    assert!( gmcrypto.decrypt(&c3) == false);
+    assert!( !gmcrypto.decrypt(&c2).unwrap() );
+    assert!( !gmcrypto.decrypt(&c3).unwrap() );
 }
 
 #[test]
 fn gm_xor() {
-    let mut gmcrypto = GM::new(1024);
+    let mut gmcrypto = GM::new(1024).unwrap();
     let c = gmcrypto.encrypt(true);
     let c1 = gmcrypto.encrypt(true);
     let c2 = gmcrypto.encrypt(false);
     let c3 = gmcrypto.encrypt(false);
-    let cc1 = gmcrypto.xor(&c, &c1);
-    let cc2 = gmcrypto.xor(&c2, &c2);
-    let cc3 = gmcrypto.xor(&c, &c3);
-    let cc4 = gmcrypto.xor(&c3, &c1);
-    assert!( gmcrypto.decrypt( &cc1 ) == false );
-    assert!( gmcrypto.decrypt( &cc2 ) == false );
-    assert!( gmcrypto.decrypt( &cc3 ) == true  );
-    assert!( gmcrypto.decrypt( &cc4 ) == true  );
+    let cc1 = gmcrypto.xor(&c, &c1).unwrap();
+    let cc2 = gmcrypto.xor(&c2, &c2).unwrap();
+    let cc3 = gmcrypto.xor(&c, &c3).unwrap();
+    let cc4 = gmcrypto.xor(&c3, &c1).unwrap();
+    assert!( !gmcrypto.decrypt( &cc1 ).unwrap() );
+    assert!( !gmcrypto.decrypt( &cc2 ).unwrap() );
+    assert!( gmcrypto.decrypt( &cc3 ).unwrap() );
+    assert!( gmcrypto.decrypt( &cc4 ).unwrap() );
+}
+
+#[test]
+fn gm_rerandomize() {
+    let mut gmcrypto = GM::new(1024).unwrap();
+    let mut rs = RandomSource::insecure_mt(1);
+    let pk = gmcrypto.pk.clone();
+    let c = gmcrypto.encrypt(true);
+    let c1 = pk.rerandomize(&mut rs, &c).unwrap();
+    let c0 = gmcrypto.encrypt(false);
+    let c2 = gmcrypto.rerandomize(&c0).unwrap();
+    assert!( c1 != c );
+    assert!( gmcrypto.decrypt( &c1 ).unwrap() );
+    assert!( !gmcrypto.decrypt( &c2 ).unwrap() );
+    assert!( gmcrypto.rerandomize(&Mpz::zero()) == Err(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn gm_validate() {
+    let mut gmcrypto = GM::new(1024).unwrap();
+    assert!( gmcrypto.pk.validate(1024) );
+    let c = gmcrypto.encrypt(true);
+    assert!( gmcrypto.pk.validate_ciphertext(&c) );
+    assert!( !gmcrypto.pk.validate_ciphertext(&Mpz::zero()) );
+    assert!( !gmcrypto.pk.validate_ciphertext(&(&c + &gmcrypto.pk.n)) );
+    assert!( !gmcrypto.pk.validate_ciphertext(&gmcrypto.sk.p) );
+    let mut bad = gmcrypto.pk.clone();
+    bad.n = &bad.n * 3u64;
+    assert!( !bad.validate(1024) );
+}
+
+#[test]
+fn gm_decrypt_invalid() {
+    let mut gmcrypto = GM::new(1024).unwrap();
+    let c = gmcrypto.encrypt(true) + &gmcrypto.pk.n;
+    assert!( gmcrypto.decrypt(&c) == Err(Error::InvalidCiphertext) );
+    let c1 = gmcrypto.encrypt(true);
+    assert!( gmcrypto.xor(&c1, &Mpz::zero()) == Err(Error::InvalidCiphertext) );
 }
 
 #[bench]
 fn bench_gm_enc(b: &mut Bencher) {
-    let mut gmcrypto = GM::new(1024);
+    let mut gmcrypto = GM::new(1024).unwrap();
     b.iter(|| { let c = gmcrypto.encrypt(false); c} );
 }
 
 #[bench]
 fn bench_gm_dec(b: &mut Bencher) {
-    let mut gmcrypto = GM::new(1024);
+    let mut gmcrypto = GM::new(1024).unwrap();
     let c = gmcrypto.encrypt(false);
-    b.iter(|| { let m = gmcrypto.decrypt(&c); m} );
+    b.iter(|| gmcrypto.decrypt(&c).unwrap() );
 }
 
 #[test]
 fn pai_gen_key() {
-    let paics = Paillier::new(1024);
+    let paics = Paillier::new(1024).unwrap();
     assert!(&paics.pk.n == &(&paics.pk.g - Mpz::one()));
 }
 
 #[test]
 fn pai_enc_dec() {
     let m : Mpz = From::<i64>::from(1235);
-    let mut paics = Paillier::new(1024);
-    let c = paics.encrypt(&m);
-    let mm = paics.decrypt(&c);
+    let mut paics = Paillier::new(1024).unwrap();
+    let c = paics.encrypt(&m).unwrap();
+    let mm = paics.decrypt(&c).unwrap();
     assert!(&m == &mm);
 }
 
 #[test]
 fn pai_add_cipher() {
-    let mut paics = Paillier::new(1024);
+    let mut paics = Paillier::new(1024).unwrap();
     let m1 : Mpz = From::<i64>::from(1235);
     let m2 : Mpz = From::<i64>::from(5321);
-    let c1 = paics.encrypt(&m1);
-    let c2 = paics.encrypt(&m2);
-    let c3 = paics.add_cipher(&c1, &c2);
-    let m3 = paics.decrypt(&c3);
+    let c1 = paics.encrypt(&m1).unwrap();
+    let c2 = paics.encrypt(&m2).unwrap();
+    let c3 = paics.add_cipher(&c1, &c2).unwrap();
+    let m3 = paics.decrypt(&c3).unwrap();
     assert!(&m3 == &From::from(6556i64));
 }
 
 #[test]
 fn pai_add_const() {
-    let mut paics = Paillier::new(1024);
+    let mut paics = Paillier::new(1024).unwrap();
     let m1 : Mpz = From::<i64>::from(1235);
     let m2 : Mpz = From::<i64>::from(5321);
-    let c1 = paics.encrypt(&m1);
-    let c3 = paics.add_const(&c1, &m2);
-    let m3 = paics.decrypt(&c3);
+    let c1 = paics.encrypt(&m1).unwrap();
+    let c3 = paics.add_const(&c1, &m2).unwrap();
+    let m3 = paics.decrypt(&c3).unwrap();
     assert!(&m3 == &From::from(6556i64));
 }
 
 #[test]
 fn pai_mul_const() {
-    let mut paics = Paillier::new(1024);
+    let mut paics = Paillier::new(1024).unwrap();
     let m1 : Mpz = From::<i64>::from(1235);
     let m2 : Mpz = From::<i64>::from(5321);
-    let c1 = paics.encrypt(&m1);
-    let c3 = paics.mul_const(&c1, &m2);
-    let m3 = paics.decrypt(&c3);
+    let c1 = paics.encrypt(&m1).unwrap();
+    let c3 = paics.mul_const(&c1, &m2).unwrap();
+    let m3 = paics.decrypt(&c3).unwrap();
     assert!(&m3 == &From::from(1235i64 * 5321));
 }
 
+#[test]
+fn pai_encryptor_decryptor() {
+    let paics = Paillier::new(1024).unwrap();
+    let mut enc = PaiEncryptor::new(paics.pk.clone()).unwrap();
+    let (_, dec) = paics.split();
+    let m1 : Mpz = From::<i64>::from(1235);
+    let m2 : Mpz = From::<i64>::from(5321);
+    let c1 = enc.encrypt(&m1).unwrap();
+    let c2 = enc.encrypt(&m2).unwrap();
+    let c3 = enc.add_cipher(&c1, &c2).unwrap();
+    let c4 = enc.mul_const(&enc.add_const(&c3, &m1).unwrap(), &m2).unwrap();
+    assert!(dec.decrypt(&c3).unwrap() == Mpz::from(6556i64));
+    assert!(dec.decrypt(&c4).unwrap() == Mpz::from((6556i64 + 1235) * 5321));
+}
+
+#[test]
+fn pai_dec_crt() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let m1 : Mpz = From::<i64>::from(1235);
+    let m2 = &paics.pk.n - Mpz::one();
+    let c1 = paics.encrypt(&m1).unwrap();
+    let c2 = paics.encrypt(&m2).unwrap();
+    assert!(paics.decrypt_crt(&c1).unwrap() == m1);
+    assert!(paics.decrypt_crt(&c2).unwrap() == m2);
+    let c3 = paics.add_cipher(&c1, &c2).unwrap();
+    assert!(paics.decrypt_crt(&c3).unwrap() == paics.decrypt(&c3).unwrap());
+}
+
+#[test]
+fn pai_fast_l() {
+    let mut paics = Paillier::new(1024).unwrap();
+    for i in 0..10i64 {
+        let c = paics.encrypt(&From::<i64>::from(i * 7919)).unwrap();
+        let u = c.powm(&paics.sk.lambda, &paics.pk.n2);
+        let slow = (&u - Mpz::one()) / &paics.pk.n;
+        assert!(paics.sk.ln.apply(&u) == slow);
+    }
+}
+
+#[test]
+fn pai_encrypt_owner() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let m1 : Mpz = From::<i64>::from(1235);
+    let m2 : Mpz = From::<i64>::from(5321);
+    let c1 = paics.encrypt_owner(&m1).unwrap();
+    let c2 = paics.encrypt(&m2).unwrap();
+    assert!( c1 != paics.encrypt_owner(&m1).unwrap() );
+    assert!(paics.decrypt(&c1).unwrap() == m1);
+    let c3 = paics.add_cipher(&c1, &c2).unwrap();
+    assert!(paics.decrypt_crt(&c3).unwrap() == Mpz::from(6556i64));
+}
+
+#[test]
+fn pai_rand_pool() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let m : Mpz = From::<i64>::from(1235);
+    let mut pool = RandPool::new(paics.pk.clone(), DryPolicy::Fail).unwrap();
+    pool.fill(2);
+    assert!(pool.len() == 2);
+    let c1 = pool.encrypt(&m).unwrap();
+    let c2 = pool.encrypt(&m).unwrap();
+    assert!( c1 != c2 );
+    assert!( pool.encrypt(&m).is_err() );
+    assert!(paics.decrypt(&c1).unwrap() == m);
+    assert!(paics.decrypt(&c2).unwrap() == m);
+
+    pool.policy = DryPolicy::Block;
+    pool.fill_background(3).unwrap();
+    for _ in 0..3 {
+        let c = pool.encrypt(&m).unwrap();
+        assert!(paics.decrypt(&c).unwrap() == m);
+    }
+    assert!( pool.encrypt(&m).is_err() );
+
+    pool.policy = DryPolicy::Compute;
+    let c = pool.encrypt(&m).unwrap();
+    assert!(paics.decrypt(&c).unwrap() == m);
+
+    // a running producer is not replaced
+    pool.fill_background(1000).unwrap();
+    assert!( pool.fill_background(1).err() == Some(Error::PoolBusy) );
+}
+
+#[test]
+fn pai_ciphertext_ops() {
+    let paics = Paillier::new(1024).unwrap();
+    let mut enc = paics.encryptor().unwrap();
+    let m1 : Mpz = From::<i64>::from(1235);
+    let m2 : Mpz = From::<i64>::from(5321);
+    let c1 = Ciphertext::encrypt(&enc.pk, &mut enc.rs, &m1).unwrap();
+    let c2 = Ciphertext::encrypt(&enc.pk, &mut enc.rs, &m2).unwrap();
+    let sum = &c1 + &c2;
+    let diff = &c2 - &c1;
+    let affine = &(&c1 * &m2) + &m1;
+    assert!(paics.decrypt_ciphertext(&sum).unwrap() == Mpz::from(6556i64));
+    assert!(paics.decrypt_ciphertext(&diff).unwrap() == Mpz::from(5321i64 - 1235));
+    assert!(paics.decrypt_ciphertext(&affine).unwrap() == Mpz::from(1235i64 * 5321 + 1235));
+    let neg = paics.decrypt_ciphertext(&-c1).unwrap();
+    assert!(neg == &paics.pk.n - &m1);
+}
+
+#[test]
+#[should_panic]
+fn pai_ciphertext_key_mismatch() {
+    let mut paics1 = Paillier::new(512).unwrap();
+    let mut paics2 = Paillier::new(512).unwrap();
+    let m : Mpz = From::<i64>::from(1235);
+    let c1 = Ciphertext::encrypt(&paics1.pk, &mut paics1.rs, &m).unwrap();
+    let c2 = Ciphertext::encrypt(&paics2.pk, &mut paics2.rs, &m).unwrap();
+    let _ = c1 + c2;
+}
+
+#[test]
+fn pai_ciphertext_checked_ops() {
+    let mut paics1 = Paillier::new(512).unwrap();
+    let mut paics2 = Paillier::new(512).unwrap();
+    let m : Mpz = From::<i64>::from(1235);
+    let c1 = Ciphertext::encrypt(&paics1.pk, &mut paics1.rs, &m).unwrap();
+    let c2 = Ciphertext::encrypt(&paics2.pk, &mut paics2.rs, &m).unwrap();
+    assert!( c1.try_add(&c2).err() == Some(Error::KeyMismatch) );
+    assert!( c1.try_sub(&c2).err() == Some(Error::KeyMismatch) );
+    let diff = c1.try_sub(&c1).unwrap();
+    assert!( paics1.decrypt_ciphertext(&diff).unwrap() == Mpz::zero() );
+    let bad = Ciphertext { pk: &paics1.pk, c: paics1.pk.n.clone() };
+    assert!( bad.try_neg().err() == Some(Error::InvalidCiphertext) );
+    assert!( bad.try_add_const(&m).err() == Some(Error::InvalidCiphertext) );
+    assert!( bad.try_mul_const(&m).err() == Some(Error::InvalidCiphertext) );
+    let sum = c1.try_add_const(&m).unwrap().try_mul_const(&Mpz::from(3u64)).unwrap();
+    assert!( paics1.decrypt_ciphertext(&sum).unwrap() == Mpz::from(7410u64) );
+}
+
+#[test]
+fn pai_signed() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let m1 : Mpz = From::<i64>::from(-1235);
+    let m2 : Mpz = From::<i64>::from(5321);
+    let c1 = paics.encrypt_signed(&m1).unwrap();
+    let c2 = paics.encrypt_signed(&m2).unwrap();
+    assert!(paics.decrypt_signed(&c1).unwrap() == m1);
+    let c3 = paics.add_cipher(&c1, &c2).unwrap();
+    assert!(paics.decrypt_signed(&c3).unwrap() == Mpz::from(4086i64));
+    let c4 = paics.add_const(&c2, &From::<i64>::from(-6000)).unwrap();
+    assert!(paics.decrypt_signed(&c4).unwrap() == Mpz::from(-679i64));
+    let c5 = paics.mul_const(&c1, &From::<i64>::from(-3)).unwrap();
+    assert!(paics.decrypt_signed(&c5).unwrap() == Mpz::from(3705i64));
+
+    let max = max_signed(&paics.pk);
+    assert!( paics.encrypt_signed(&(&max + Mpz::one())).is_err() );
+    assert!( paics.encrypt_signed(&-(&max + Mpz::one())).is_err() );
+    let cmax = paics.encrypt_signed(&max).unwrap();
+    let cover = paics.add_cipher(&cmax, &cmax).unwrap();
+    assert!( paics.decrypt_signed(&cover).is_err() );
+    let emin = encode_signed(&paics.pk, &-&max).unwrap();
+    assert!(decode_signed(&paics.pk, &emin).unwrap() == -&max);
+}
+
+#[test]
+fn pai_encoded_number() {
+    let mut paics = Paillier::new(1024).unwrap();
+    for &x in [0.0, 1.0, -3.5, 0.1, 1e-300, -123456.789, 5e-324, 1e300].iter() {
+        let e = EncodedNumber::encode_f64(&paics.pk, x).unwrap();
+        assert!(e.decode_f64(&paics.pk).unwrap() == x);
+        let c = paics.encrypt_f64(x).unwrap();
+        assert!(paics.decrypt_f64(&c).unwrap() == x);
+    }
+    assert!( paics.encrypt_f64(f64::NAN).is_err() );
+
+    let c1 = paics.encrypt_f64(1.5).unwrap();
+    let c2 = paics.encrypt_f64(-0.0625).unwrap();
+    let sum = c1.add(&paics.pk, &c2).unwrap();
+    assert!(paics.decrypt_f64(&sum).unwrap() == 1.4375);
+    let w = EncodedNumber::encode_f64(&paics.pk, -2.25).unwrap();
+    let prod = sum.mul_encoded(&paics.pk, &w).unwrap();
+    assert!(paics.decrypt_f64(&prod).unwrap() == 1.4375 * -2.25);
+    let k = EncodedNumber::encode_f64(&paics.pk, 100.0).unwrap();
+    let shifted = prod.add_encoded(&paics.pk, &k).unwrap();
+    assert!(paics.decrypt_f64(&shifted).unwrap() == 1.4375 * -2.25 + 100.0);
+
+    let c3 = paics.encrypt_f64(0.1).unwrap();
+    let c4 = paics.encrypt_f64(0.2).unwrap();
+    let sum = c3.add(&paics.pk, &c4).unwrap();
+    assert!((paics.decrypt_f64(&sum).unwrap() - 0.3).abs() < 1e-15);
+
+    let far = EncodedNumber::new(&paics.pk, &Mpz::one(), i32::MAX).unwrap();
+    assert!(far.decrease_exponent_to(&paics.pk, -10).err() == Some(Error::PlaintextOverflow));
+    assert!(far.decrease_exponent_to(&paics.pk, i32::MAX - 256).err() == Some(Error::PlaintextOverflow));
+    let cfar = EncryptedNumber { ciphertext: c3.ciphertext.clone(), exponent: i32::MAX };
+    assert!(cfar.decrease_exponent_to(&paics.pk, -10).err() == Some(Error::PlaintextOverflow));
+    assert!(cfar.mul_encoded(&paics.pk, &far).err() == Some(Error::PlaintextOverflow));
+}
+
+#[test]
+fn pai_packing() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let packer = Packer::new(&paics.pk, 32, 4).unwrap();
+    assert!(packer.lanes == 1023 / 37);
+    let v1 : Vec<u64> = (0..packer.lanes as u64).map(|i| i * 1000).collect();
+    let v2 : Vec<u64> = (0..packer.lanes as u64).map(|i| 0xffff_ffff - i).collect();
+    let c1 = paics.encrypt(&packer.pack(&v1).unwrap()).unwrap();
+    let c2 = paics.encrypt(&packer.pack(&v2).unwrap()).unwrap();
+    let sum = packer.unpack_sum(&paics.decrypt(&paics.add_cipher(&c1, &c2).unwrap()).unwrap(), 2).unwrap();
+    for i in 0..packer.lanes {
+        assert!(sum[i] == v1[i] + v2[i]);
+    }
+    assert!(packer.pack(&[1u64 << 32]).is_err());
+    assert!(packer.unpack(&packer.pack(&[7, 8]).unwrap()).unwrap()[..3] == [7, 8, 0]);
+
+    let max = packer.pack(&[0xffff_ffff]).unwrap();
+    let mut c = paics.encrypt(&max).unwrap();
+    for _ in 0..15 {
+        let ci = paics.encrypt(&max).unwrap();
+        c = paics.add_cipher(&c, &ci).unwrap();
+    }
+    assert!(packer.unpack_sum(&paics.decrypt(&c).unwrap(), 16).unwrap()[0] == 16 * 0xffff_ffff);
+    for _ in 0..16 {
+        let ci = paics.encrypt(&max).unwrap();
+        c = paics.add_cipher(&c, &ci).unwrap();
+    }
+    assert!(packer.unpack(&paics.decrypt(&c).unwrap()).is_err());
+
+    // 40 sums outgrow the 37-bit lane by more than one bit, the guard bit
+    // clears again but the count gives the overflow away
+    let lanes = packer.pack(&[0xffff_ffff, 0]).unwrap();
+    let mut c = paics.encrypt(&lanes).unwrap();
+    for _ in 1..40 {
+        let ci = paics.encrypt(&lanes).unwrap();
+        c = paics.add_cipher(&c, &ci).unwrap();
+    }
+    assert!(packer.unpack_sum(&paics.decrypt(&c).unwrap(), 40).is_err());
+    assert!(packer.unpack_sum(&packer.pack(&[1, 2]).unwrap(), 17).is_err());
+    let top = Mpz::from(40 * 0xffff_ffffu64) << ((packer.lanes - 1) * packer.lane_bits());
+    assert!(packer.unpack(&top).is_err());
+}
+
+#[test]
+fn pai_rerandomize() {
+    let paics = Paillier::new(1024).unwrap();
+    let mut enc = paics.encryptor().unwrap();
+    let m1 : Mpz = From::<i64>::from(1235);
+    let m2 : Mpz = From::<i64>::from(5321);
+    let c1 = enc.encrypt(&m1).unwrap();
+    let c2 = enc.rerandomize(&c1).unwrap();
+    assert!( c1 != c2 );
+    assert!( enc.rerandomize(&Mpz::zero()) == Err(Error::InvalidCiphertext) );
+    assert!(paics.sk.decrypt(&paics.pk, &c2).unwrap() == m1);
+
+    // obfuscate lazily: only the exported sum gets an r^n factor
+    let raw = enc.add_cipher(&enc.pk.raw_encrypt(&m1), &enc.pk.raw_encrypt(&m2)).unwrap();
+    let out = enc.rerandomize(&raw).unwrap();
+    assert!( raw != out );
+    assert!(paics.sk.decrypt(&paics.pk, &out).unwrap() == Mpz::from(6556i64));
+
+    let mut pool = RandPool::new(paics.pk.clone(), DryPolicy::Compute).unwrap();
+    let c3 = pool.rerandomize(&c1).unwrap();
+    assert!(paics.sk.decrypt(&paics.pk, &c3).unwrap() == m1);
+    assert!( pool.rerandomize(&Mpz::zero()) == Err(Error::InvalidCiphertext) );
+    let ct = Ciphertext::new(&enc.pk, c3).unwrap();
+    let ct2 = ct.rerandomize(&mut enc.rs).unwrap();
+    assert!(paics.decrypt_ciphertext(&ct2).unwrap() == m1);
+}
+
+#[test]
+fn encrypt_with_randomness() {
+    let mut paics = Paillier::from_seed(512, b"nonce").unwrap();
+    let m = Mpz::from(1234u64);
+    let (c, r) = paics.encrypt_returning_randomness(&m).unwrap();
+    assert!( paics.encrypt_with_randomness(&m, &r).unwrap() == c );
+    assert!( paics.decrypt(&c).unwrap() == m );
+    let rn = r.powm(&paics.pk.n, &paics.pk.n2);
+    assert!( paics.pk.encrypt_with_rn(&m, &rn) == c );
+
+    // known answer: with r = 1 the ciphertext is 1 + m*n
+    let c1 = paics.encrypt_with_randomness(&m, &Mpz::one()).unwrap();
+    assert!( c1 == &m * &paics.pk.n + Mpz::one() );
+
+    assert!( paics.encrypt_with_randomness(&m, &Mpz::zero()) == Err(Error::InvalidRandomness) );
+    assert!( paics.encrypt_with_randomness(&m, &paics.pk.n) == Err(Error::InvalidRandomness) );
+    assert!( paics.encrypt_with_randomness(&m, &paics.sk.p) == Err(Error::InvalidRandomness) );
+    assert!( paics.encrypt_with_randomness(&paics.pk.n, &r) == Err(Error::PlaintextOverflow) );
+
+    let mut gmcrypto = GM::from_seed(512, b"nonce").unwrap();
+    let (c, y) = gmcrypto.encrypt_returning_randomness(true);
+    assert!( gmcrypto.encrypt_with_randomness(true, &y).unwrap() == c );
+    assert!( gmcrypto.decrypt(&c).unwrap() );
+    let c0 = gmcrypto.encrypt_with_randomness(false, &Mpz::from(3u64)).unwrap();
+    assert!( c0 == Mpz::from(9u64) );
+    assert!( !gmcrypto.decrypt(&c0).unwrap() );
+    assert!( gmcrypto.encrypt_with_randomness(true, &gmcrypto.sk.q) == Err(Error::InvalidRandomness) );
+}
+
+#[test]
+fn decrypt_with_randomness() {
+    let mut paics = Paillier::from_seed(1024, b"opening").unwrap();
+    let m1 = Mpz::from(1235u64);
+    let m2 = Mpz::from(5321u64);
+    let (c1, r1) = paics.encrypt_returning_randomness(&m1).unwrap();
+    let (c2, r2) = paics.encrypt_returning_randomness(&m2).unwrap();
+    assert!( paics.decrypt_with_randomness(&c1).unwrap() == (m1.clone(), r1.clone()) );
+
+    // the opening of a sum is the sum of plaintexts and product of nonces
+    let c3 = paics.add_cipher(&c1, &c2).unwrap();
+    let (m3, r3) = paics.decrypt_with_randomness(&c3).unwrap();
+    assert!( m3 == &m1 + &m2 );
+    assert!( r3 == (&r1 * &r2) % &paics.pk.n );
+    assert!( paics.encrypt_with_randomness(&m3, &r3).unwrap() == c3 );
+
+    let (_, dec) = paics.split();
+    let c4 = dec.pk.encrypt_with_randomness(&m2, &r1).unwrap();
+    assert!( dec.decrypt_with_randomness(&c4).unwrap() == (m2, r1) );
+    assert!( dec.decrypt_with_randomness(&Mpz::zero()) == Err(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn pai_validate() {
+    let mut paics = Paillier::new(1024).unwrap();
+    assert!( paics.pk.validate(1024) );
+    assert!( !paics.pk.validate(2048) );
+    let mut bad = paics.pk.clone();
+    bad.g = &bad.g + Mpz::one();
+    assert!( !bad.validate(1024) );
+    let bad = PaiPk::from_n(&paics.pk.n * 3u64);
+    assert!( !bad.validate(bad.n.bit_length()) );
+
+    let c = Ciphertext::encrypt(&paics.pk, &mut paics.rs, &Mpz::one()).unwrap();
+    assert!( c.validate() );
+    assert!( !paics.pk.validate_ciphertext(&Mpz::zero()) );
+    assert!( !paics.pk.validate_ciphertext(&(&c.c + &paics.pk.n2)) );
+    assert!( !paics.pk.validate_ciphertext(&(&paics.sk.p * 5u64)) );
+    assert!( !paics.sk.validate_ciphertext(&paics.pk.n2) );
+}
+
+#[test]
+fn pai_decrypt_invalid() {
+    let mut paics = Paillier::new(1024).unwrap();
+    assert!( paics.decrypt(&Mpz::zero()) == Err(Error::InvalidCiphertext) );
+    let n2 = paics.pk.n2.clone();
+    assert!( paics.decrypt_crt(&n2) == Err(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn pai_add_invalid() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let c = paics.encrypt(&Mpz::one()).unwrap();
+    assert!( paics.add_cipher(&c, &paics.pk.n) == Err(Error::InvalidCiphertext) );
+    assert!( paics.mul_const(&Mpz::zero(), &Mpz::one()) == Err(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn error_results() {
+    assert!( Paillier::new(1023).err() == Some(Error::InvalidKeySize) );
+    assert!( GM::new(64).err() == Some(Error::InvalidKeySize) );
+    assert!( DamgardJurik::new(512, 0).err() == Some(Error::InvalidKeySize) );
+    assert!( DjPk::new(Mpz::from(6u64 * 1_000_003), 2).err() == Some(Error::InvalidKey) );
+    assert!( DjPk::new(Mpz::zero(), 1).err() == Some(Error::InvalidKey) );
+    assert!( ThresholdPk::deal(512, 4, 3).err() == Some(Error::InvalidThreshold) );
+
+    let mut paics = Paillier::new(512).unwrap();
+    let n = paics.pk.n.clone();
+    assert!( paics.encrypt(&n) == Err(Error::PlaintextOverflow) );
+    assert!( paics.encrypt(&-Mpz::one()) == Err(Error::PlaintextOverflow) );
+    assert!( paics.encrypt_owner(&n) == Err(Error::PlaintextOverflow) );
+
+    let pk = PaiPk::from_str_radix(&n.to_str_radix(16), 16).unwrap();
+    assert!( pk.n == n );
+    assert!( PaiPk::from_str_radix("12xyz", 10).err() == Some(Error::Parse) );
+    assert!( PaiPk::from_str_radix("10", 1).err() == Some(Error::Parse) );
+    assert!( PaiPk::from_str_radix("1234", 10).err() == Some(Error::InvalidKey) );
+
+    let c = paics.encrypt(&Mpz::from(7u64)).unwrap();
+    let ct = Ciphertext::from_str_radix(&paics.pk, &c.to_str_radix(36), 36).unwrap();
+    assert!( paics.decrypt_ciphertext(&ct).unwrap() == Mpz::from(7u64) );
+    assert!( Ciphertext::from_str_radix(&paics.pk, "0", 10).err() == Some(Error::InvalidCiphertext) );
+    let other = Paillier::new(512).unwrap();
+    assert!( other.decrypt_ciphertext(&ct) == Err(Error::KeyMismatch) );
+
+    let gmcrypto = GM::new(512).unwrap();
+    let gpk = GmPk::from_str_radix(&gmcrypto.pk.n.to_str_radix(10), &gmcrypto.pk.x.to_str_radix(10), 10).unwrap();
+    assert!( gpk.x == gmcrypto.pk.x );
+    assert!( GmPk::from_str_radix(&gmcrypto.pk.n.to_str_radix(10), "0", 10).err() == Some(Error::InvalidKey) );
+    assert!( format!("{}", Error::PlaintextOverflow) == "plaintext overflow" );
+}
+
 #[bench]
 fn bench_pai_enc(b: &mut Bencher) {
     let m : Mpz = From::<i64>::from(1235);
-    let mut paics = Paillier::new(1024);
-    b.iter(|| { let c = paics.encrypt(&m); c} );
+    let mut paics = Paillier::new(1024).unwrap();
+    b.iter(|| paics.encrypt(&m).unwrap() );
+}
+
+#[bench]
+fn bench_pai_enc_owner(b: &mut Bencher) {
+    let m : Mpz = From::<i64>::from(1235);
+    let mut paics = Paillier::new(1024).unwrap();
+    b.iter(|| paics.encrypt_owner(&m).unwrap() );
+}
+
+#[bench]
+fn bench_pai_enc_pool(b: &mut Bencher) {
+    let m : Mpz = From::<i64>::from(1235);
+    let paics = Paillier::new(1024).unwrap();
+    let mut pool = RandPool::new(paics.pk.clone(), DryPolicy::Compute).unwrap();
+    let rn = pool.take().unwrap();
+    b.iter(|| paics.pk.encrypt_with_rn(&m, &rn) );
 }
 
 #[bench]
 fn bench_pai_dec(b: &mut Bencher) {
     let m : Mpz = From::<i64>::from(1235);
-    let mut paics = Paillier::new(1024);
-    let c = paics.encrypt(&m);
-    b.iter(|| { let m = paics.decrypt(&c); m} );
+    let mut paics = Paillier::new(1024).unwrap();
+    let c = paics.encrypt(&m).unwrap();
+    b.iter(|| paics.decrypt(&c).unwrap() );
 }
+
+#[bench]
+fn bench_pai_dec_crt(b: &mut Bencher) {
+    let m : Mpz = From::<i64>::from(1235);
+    let mut paics = Paillier::new(1024).unwrap();
+    let c = paics.encrypt(&m).unwrap();
+    b.iter(|| paics.decrypt_crt(&c).unwrap() );
+}
+
+#[test]
+fn threshold_decrypt() {
+    let (tpk, shares) = ThresholdPk::deal(512, 3, 5).unwrap();
+    let mut rs = RandomSource::insecure_mt(7);
+    let m1 : Mpz = From::<i64>::from(1235);
+    let m2 : Mpz = From::<i64>::from(5321);
+    let c = tpk.pk.add_cipher(&tpk.encrypt(&mut rs, &m1).unwrap(), &tpk.encrypt(&mut rs, &m2).unwrap()).unwrap();
+    let parts : Vec<_> = shares.iter().map(|s| s.partial_decrypt(&tpk, &c)).collect();
+
+    assert!(tpk.combine(&parts[..3]).unwrap() == Mpz::from(6556i64));
+    assert!(tpk.combine(&parts[2..]).unwrap() == Mpz::from(6556i64));
+    let subset = vec![parts[4].clone(), parts[0].clone(), parts[3].clone()];
+    assert!(tpk.combine(&subset).unwrap() == Mpz::from(6556i64));
+    assert!(tpk.combine(&parts).unwrap() == Mpz::from(6556i64));
+    assert!( tpk.combine(&parts[..2]).is_err() );
+    let dup = vec![parts[0].clone(), parts[0].clone(), parts[1].clone()];
+    assert!( tpk.combine(&dup).is_err() );
+
+    // shares of n and 0 are not units and are rejected instead of panicking
+    let mut bad = parts[..3].to_vec();
+    bad[0].ci = tpk.pk.n.clone();
+    bad[1].ci = Mpz::zero();
+    assert!( tpk.combine(&bad).err() == Some(Error::InvalidCiphertext) );
+    bad[1] = parts[1].clone();
+    assert!( tpk.combine(&bad).err() == Some(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn threshold_decryption_proofs() {
+    let (tpk, shares) = ThresholdPk::deal(512, 2, 4).unwrap();
+    let mut rs = RandomSource::insecure_mt(7);
+    let m : Mpz = From::<i64>::from(1235);
+    let c = tpk.encrypt(&mut rs, &m).unwrap();
+    let mut parts : Vec<_> = shares.iter().map(|s| s.partial_decrypt_with_proof(&tpk, &mut rs, &c)).collect();
+    for part in parts.iter() {
+        assert!( tpk.verify_partial(&c, part) );
+    }
+    assert!( tpk.find_cheaters(&c, &parts).is_empty() );
+
+    // share holder 1 lies about its partial decryption, holder 3 uses a wrong share
+    parts[0].ci = (&parts[0].ci * &parts[0].ci) % &tpk.pk.n2;
+    let mut bad = shares[3].partial_decrypt_with_proof(&tpk, &mut rs, &c);
+    bad.i = 3;
+    parts[2] = bad;
+    parts[3] = shares[3].partial_decrypt(&tpk, &c);
+    assert!( tpk.find_cheaters(&c, &parts) == vec![1, 3, 4] );
+    assert!( tpk.combine_verified(&c, &parts).is_err() );
+    parts[3] = shares[3].partial_decrypt_with_proof(&tpk, &mut rs, &c);
+    assert!(tpk.combine_verified(&c, &parts).unwrap() == m);
+
+    // forged proofs with negative responses are rejected, also for a non-unit c
+    let mut forged = parts[1].clone();
+    forged.proof.as_mut().unwrap().z = -Mpz::one();
+    assert!( !tpk.verify_partial(&c, &forged) );
+    assert!( !tpk.verify_partial(&tpk.pk.n, &forged) );
+    let mut forged = parts[1].clone();
+    forged.proof.as_mut().unwrap().e = -Mpz::one();
+    assert!( !tpk.verify_partial(&c, &forged) );
+    assert!( tpk.find_cheaters(&tpk.pk.n, &parts[1..2]) == vec![2] );
+}
+
+#[test]
+fn zk_plaintext_knowledge() {
+    let paics = Paillier::new(1024).unwrap();
+    let mut enc = paics.encryptor().unwrap();
+    let m : Mpz = From::<i64>::from(1235);
+    let (c, proof) = PlaintextProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &m, b"client 1").unwrap();
+    assert!( proof.verify(&enc.pk, &c, b"client 1") );
+    assert!( !proof.verify(&enc.pk, &c, b"client 2") );
+
+    // a mauled ciphertext cannot reuse the proof
+    let c2 = enc.add_const(&c, &Mpz::one()).unwrap();
+    assert!( !proof.verify(&enc.pk, &c2, b"client 1") );
+    let c3 = enc.rerandomize(&c).unwrap();
+    assert!( !proof.verify(&enc.pk, &c3, b"client 1") );
+    let mut forged = proof.clone();
+    forged.z = (&forged.z + Mpz::one()) % &enc.pk.n;
+    assert!( !forged.verify(&enc.pk, &c, b"client 1") );
+}
+
+#[test]
+fn zk_range() {
+    let paics = Paillier::new(1024).unwrap();
+    let mut enc = paics.encryptor().unwrap();
+    let bound : Mpz = From::<i64>::from(1000);
+    for &v in [0i64, 1, 537, 999].iter() {
+        let m : Mpz = From::<i64>::from(v);
+        let (c, proof) = RangeProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &m, &bound, b"meter 7").unwrap();
+        assert!( proof.verify(&enc.pk, &c, &bound, b"meter 7") );
+        assert!( !proof.verify(&enc.pk, &c, &bound, b"meter 8") );
+        assert!( !proof.verify(&enc.pk, &c, &From::<i64>::from(1024), b"meter 7") );
+    }
+    assert!( RangeProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &bound, &bound, b"").err() == Some(Error::InvalidPlaintext) );
+    let neg = &enc.pk.n - Mpz::from(1000u64);
+    assert!( RangeProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &neg, &bound, b"").err() == Some(Error::InvalidPlaintext) );
+
+    // non-unit ciphertexts and randomness are rejected instead of panicking
+    let m : Mpz = From::<i64>::from(5);
+    let r = enc.pk.random_unit(&mut enc.rs);
+    let n = enc.pk.n.clone();
+    assert!( RangeProof::prove(&enc.pk, &mut enc.rs, &n, &m, &r, &bound, b"").err() == Some(Error::InvalidCiphertext) );
+    assert!( RangeProof::prove(&enc.pk, &mut enc.rs, &Mpz::zero(), &m, &r, &bound, b"").err() == Some(Error::InvalidCiphertext) );
+    let c = enc.pk.encrypt_with_rn(&m, &r.powm(&enc.pk.n, &enc.pk.n2));
+    assert!( RangeProof::prove(&enc.pk, &mut enc.rs, &c, &m, &n, &bound, b"").err() == Some(Error::InvalidRandomness) );
+
+    // power of two bound needs no upper decomposition
+    let bound : Mpz = From::<i64>::from(256);
+    let m : Mpz = From::<i64>::from(255);
+    let (c, proof) = RangeProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &m, &bound, b"").unwrap();
+    assert!( proof.upper.is_none() );
+    assert!( proof.verify(&enc.pk, &c, &bound, b"") );
+    let c2 = enc.add_const(&c, &Mpz::one()).unwrap();
+    assert!( !proof.verify(&enc.pk, &c2, &bound, b"") );
+}
+
+#[test]
+fn zk_membership() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let mut enc = paics.encryptor().unwrap();
+    let mut tally = paics.encrypt(&Mpz::zero()).unwrap();
+    for &vote in [true, false, true, true].iter() {
+        let (c, proof) = ZeroOneProof::encrypt_and_prove(&enc.pk, &mut enc.rs, vote, b"election 1").unwrap();
+        assert!( proof.verify(&enc.pk, &c, b"election 1") );
+        assert!( !proof.verify(&enc.pk, &c, b"election 2") );
+        tally = enc.add_cipher(&tally, &c).unwrap();
+    }
+    assert!(paics.decrypt(&tally).unwrap() == Mpz::from(3i64));
+
+    // a ballot for 2 cannot be proven, and a valid proof does not transfer to it
+    let (c, proof) = ZeroOneProof::encrypt_and_prove(&enc.pk, &mut enc.rs, true, b"").unwrap();
+    let c2 = enc.add_const(&c, &Mpz::one()).unwrap();
+    assert!( !proof.verify(&enc.pk, &c2, b"") );
+
+    let set : Vec<Mpz> = vec![From::<i64>::from(-5), From::<i64>::from(10), From::<i64>::from(20)];
+    let m : Mpz = From::<i64>::from(-5);
+    let (c, proof) = MembershipProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &set, &m, b"").unwrap();
+    assert!( proof.verify(&enc.pk, &c, &set, b"") );
+    assert!( !proof.verify(&enc.pk, &c, &set[1..], b"") );
+    assert!( MembershipProof::encrypt_and_prove(&enc.pk, &mut enc.rs, &set, &Mpz::one(), b"").err() == Some(Error::InvalidPlaintext) );
+    let r = enc.pk.random_unit(&mut enc.rs);
+    let n = enc.pk.n.clone();
+    assert!( ZeroOneProof::prove(&enc.pk, &mut enc.rs, &n, true, &r, b"").err() == Some(Error::InvalidCiphertext) );
+    assert!( ZeroOneProof::prove(&enc.pk, &mut enc.rs, &Mpz::zero(), true, &r, b"").err() == Some(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn zk_affine() {
+    let mut alice = Paillier::new(1024).unwrap();
+    let mut bob = Paillier::new(1024).unwrap();
+    let m : Mpz = From::<i64>::from(1235);
+    let a : Mpz = From::<i64>::from(5321);
+    let b : Mpz = From::<i64>::from(777);
+    let c = alice.encrypt(&m).unwrap();
+
+    let (st, proof) = AffineProof::apply_and_prove(&alice.pk, &bob.pk, &mut bob.rs, &c, (&a, &b), 64, b"mta").unwrap();
+    assert!( proof.verify(&alice.pk, &bob.pk, &c, &st, 64, b"mta") );
+    assert!( !proof.verify(&alice.pk, &bob.pk, &c, &st, 64, b"other") );
+    assert!(alice.decrypt(&st.d).unwrap() == Mpz::from(1235i64 * 5321 + 777));
+    assert!(bob.decrypt(&st.x).unwrap() == a);
+    assert!(bob.decrypt(&st.y).unwrap() == b);
+
+    // d not matching the committed constants is rejected
+    let mut bad = st.clone();
+    bad.d = alice.add_const(&st.d, &Mpz::one()).unwrap();
+    assert!( !proof.verify(&alice.pk, &bob.pk, &c, &bad, 64, b"mta") );
+    let mut bad = st.clone();
+    bad.x = bob.add_const(&st.x, &Mpz::one()).unwrap();
+    assert!( !proof.verify(&alice.pk, &bob.pk, &c, &bad, 64, b"mta") );
+    let big = Mpz::one() << 64;
+    assert!( AffineProof::apply_and_prove(&alice.pk, &bob.pk, &mut bob.rs, &c, (&big, &b), 64, b"").err() == Some(Error::PlaintextOverflow) );
+    let n = alice.pk.n.clone();
+    assert!( AffineProof::apply_and_prove(&alice.pk, &bob.pk, &mut bob.rs, &n, (&a, &b), 64, b"").err() == Some(Error::InvalidCiphertext) );
+}
+
+#[test]
+fn zk_modulus() {
+    let mut paics = Paillier::new(1024).unwrap();
+    let proof = ModulusProof::prove(&paics.pk, &paics.sk, &mut paics.rs, b"bank A").unwrap();
+    assert!( proof.verify(&paics.pk.n, b"bank A") );
+    assert!( !proof.verify(&paics.pk.n, b"bank B") );
+    let pk = PaiPk::import(paics.pk.n.clone(), &proof, b"bank A").unwrap();
+    assert!(pk.g == paics.pk.g);
+    assert!( PaiPk::import(&paics.pk.n + Mpz::from(2u64), &proof, b"bank A").is_err() );
+
+    // n = p^2 q has no n-th roots to offer, a proof for another modulus fails
+    let mut rs = RandomSource::insecure_mt(3);
+    let p = generate_strong_prime(&mut rs, 256);
+    let bad_n = &p * &p * generate_strong_prime(&mut rs, 512);
+    assert!( !proof.square_free.verify(&bad_n, b"bank A") );
+    let forged = SquareFreeProof { sigmas: proof.square_free.sigmas.iter().map(|s| s % &bad_n).collect() };
+    assert!( !forged.verify(&bad_n, b"bank A") );
+    assert!( !proof.blum.verify(&bad_n, b"bank A") );
+}
+
+#[test]
+fn dj_enc_dec() {
+    for s in 1..4 {
+        let mut dj = DamgardJurik::new(512, s).unwrap();
+        let m1 : Mpz = From::<i64>::from(1235);
+        let m2 = &dj.pk.ns - Mpz::one();
+        let c1 = dj.encrypt(&m1).unwrap();
+        let c2 = dj.encrypt(&m2).unwrap();
+        assert!( c1 < dj.pk.ns1 );
+        assert!(dj.decrypt(&c1).unwrap() == m1);
+        assert!(dj.decrypt(&c2).unwrap() == m2);
+        let c3 = dj.add_cipher(&c1, &c2);
+        assert!(dj.decrypt(&c3).unwrap() == Mpz::from(1234i64));
+        let c4 = dj.mul_const(&dj.add_const(&c1, &From::<i64>::from(5321)), &From::<i64>::from(-1));
+        assert!(dj.decrypt(&c4).unwrap() == &dj.pk.ns - Mpz::from(6556u64));
+    }
+}
+
+#[bench]
+fn bench_dj_dec(b: &mut Bencher) {
+    let m : Mpz = From::<i64>::from(1235);
+    let mut dj = DamgardJurik::new(1024, 3).unwrap();
+    let c = dj.encrypt(&m).unwrap();
+    b.iter(|| dj.decrypt(&c).unwrap() );
+}
+
 #[bench]
 fn bench_slow_l(b: &mut Bencher) {
-    let mut rng = rand::thread_rng();
-    let mut randstate = RandState::new();
-    randstate.seed_ui( rng.gen::<u64>() );
+    let mut randstate = RandomSource::new().unwrap();
     let mut n = generate_urandom(&mut randstate, 1024);
     n.setbit(0);
     let u = generate_urandom(&mut randstate, 1024) * &n + Mpz::one();
@@ -146,9 +922,7 @@ fn bench_slow_l(b: &mut Bencher) {
 
 #[bench]
 fn bench_fast_l(b: &mut Bencher) {
-    let mut rng = rand::thread_rng();
-    let mut randstate = RandState::new();
-    randstate.seed_ui( rng.gen::<u64>() );
+    let mut randstate = RandomSource::new().unwrap();
     let mut n = generate_urandom(&mut randstate, 1024);
     n.setbit(0);
     let u = generate_urandom(&mut randstate, 1024) * &n + Mpz::one();
@@ -0,0 +1,237 @@
+//! Threshold Paillier decryption with a trusted dealer, following Shoup's
+//! threshold RSA as adapted to Paillier by Damgård and Jurik (s = 1).
+//!
+//! The dealer splits the secret key into l shares so that any t of them can
+//! decrypt, while fewer learn nothing about the key.
+
+use gmp::mpz::Mpz;
+use paillier::PaiPk;
+use rng::{generate_safe_prime, check_key_size, Randomness, RandomSource};
+use error::{Error, Result};
+use zk::{challenge, random_mask, CHALLENGE_BITS, STAT_BITS};
+
+#[derive(Clone)]
+pub struct ThresholdPk {
+    pub pk    : PaiPk,
+    pub t     : usize,
+    pub l     : usize,
+    pub delta : Mpz,        // l!
+    pub v     : Mpz,        // verification base, a random square mod n^2
+    pub vks   : Vec<Mpz>,   // vks[i-1] = v^(delta*s_i) mod n^2
+}
+
+/// Secret key share s_i = f(i) of share holder i, numbered from 1
+pub struct KeyShare {
+    pub i  : usize,
+    pub si : Mpz,
+}
+
+/// c^(2*delta*s_i) mod n^2 from share holder i
+#[derive(Clone)]
+pub struct PartialDecryption {
+    pub i     : usize,
+    pub ci    : Mpz,
+    pub proof : Option<DecryptionProof>,
+}
+
+/// Chaum-Pedersen style proof that log_{c^4}(ci^2) = log_v(vk_i), i.e. that
+/// the partial decryption used the share matching the verification key
+#[derive(Clone)]
+pub struct DecryptionProof {
+    pub e : Mpz,
+    pub z : Mpz,
+}
+
+const PROOF_TAG: &str = "threshold-paillier-partial-decryption";
+
+/// b^e mod m for a possibly negative e, `Error::NotInvertible` if e < 0 and
+/// b is not a unit mod m
+fn powm_signed(b: &Mpz, e: &Mpz, m: &Mpz) -> Result<Mpz> {
+    if *e < Mpz::zero() {
+        Ok(b.invert(m).ok_or(Error::NotInvertible)?.powm(&-e, m))
+    } else {
+        Ok(b.powm(e, m))
+    }
+}
+
+impl ThresholdPk {
+
+    /// Run the trusted dealer, returns the public key and the l key shares
+    /// `Error::InvalidKeySize` for a bad keysize and `Error::InvalidThreshold`
+    /// unless 1 <= t <= l
+    pub fn deal(keysize: usize, t: usize, l: usize) -> Result<(ThresholdPk, Vec<KeyShare>)> {
+        let mut randstate = RandomSource::new()?;
+        ThresholdPk::deal_with_rng(&mut randstate, keysize, t, l)
+    }
+
+    /// Like `deal`, drawing the primes and polynomial from `rs`
+    pub fn deal_with_rng(rs: &mut dyn Randomness, keysize: usize, t: usize, l: usize) -> Result<(ThresholdPk, Vec<KeyShare>)> {
+        ThresholdPk::generate_key(rs, keysize, t, l)
+    }
+
+    fn generate_key(randstate: &mut dyn Randomness, keysize: usize, t: usize, l: usize) -> Result<(ThresholdPk, Vec<KeyShare>)> {
+        check_key_size(keysize)?;
+        if t < 1 || t > l {
+            return Err(Error::InvalidThreshold);
+        }
+
+        let p = generate_safe_prime(randstate, keysize/2);
+        let mut q = generate_safe_prime(randstate, keysize/2);
+        while p == q {
+            q = generate_safe_prime(randstate, keysize/2);
+        }
+
+        let n = &p * &q;
+        let n2 = &n * &n;
+        let g = &n + Mpz::one();
+        let m = ((&p - Mpz::one()) / 2u64) * ((&q - Mpz::one()) / 2u64);
+        let nm = &n * &m;
+
+        // d = 0 mod m, d = 1 mod n
+        let d = &m * m.invert(&n).ok_or(Error::NotInvertible)?;
+
+        // f(X) = d + a_1 X + ... + a_{t-1} X^{t-1} mod nm
+        let mut coeffs = vec![d];
+        for _ in 1..t {
+            coeffs.push(randstate.urandom(&nm));
+        }
+        let shares: Vec<KeyShare> = (1..l + 1).map(|i| {
+            let x = Mpz::from(i as u64);
+            let si = coeffs.iter().rev().fold(Mpz::zero(), |acc, a| (acc * &x + a) % &nm);
+            KeyShare { i, si }
+        }).collect();
+
+        let delta = (2..l as u64 + 1).fold(Mpz::one(), |acc, i| acc * i);
+        let r = randstate.urandom(&n2);
+        let v = (&r * &r) % &n2;
+        let vks = shares.iter().map(|s| v.powm(&(&delta * &s.si), &n2)).collect();
+
+        let pk = PaiPk { n, n2, g };
+        Ok((ThresholdPk { pk, t, l, delta, v, vks }, shares))
+    }
+
+    pub fn encrypt(&self, rs: &mut dyn Randomness, m: &Mpz) -> Result<Mpz> {
+        self.pk.encrypt(rs, m)
+    }
+
+    /// delta * lambda^S_{0,i}, an integer for every subset S of {1..l}
+    fn lagrange(&self, set: &[usize], i: usize) -> Mpz {
+        let mut num = self.delta.clone();
+        let mut den = Mpz::one();
+        for &j in set {
+            if j != i {
+                num = num * (j as i64);
+                den = den * (j as i64 - i as i64);
+            }
+        }
+        num / den
+    }
+
+    fn proof_challenge(&self, i: usize, c4: &Mpz, ci2: &Mpz, a: &Mpz, b: &Mpz) -> Mpz {
+        let idx = Mpz::from(i as u64);
+        challenge(PROOF_TAG, &[], &[&self.pk.n, &self.v, &self.vks[i - 1], &idx, c4, ci2, a, b])
+    }
+
+    /// Check the proof attached to a partial decryption of c
+    pub fn verify_partial(&self, c: &Mpz, part: &PartialDecryption) -> bool {
+        let proof = match part.proof {
+            Some(ref proof) => proof,
+            None => return false,
+        };
+        if part.i == 0 || part.i > self.l {
+            return false;
+        }
+        // a negative exponent on a non-unit would make GMP divide by zero
+        if proof.z < Mpz::zero() || proof.e < Mpz::zero() || !self.pk.validate_ciphertext(c) {
+            return false;
+        }
+
+        let n2 = &self.pk.n2;
+        let c4 = c.powm(&Mpz::from(4u64), n2);
+        let ci2 = (&part.ci * &part.ci) % n2;
+        let (ci2_inv, vk_inv) = match (ci2.invert(n2), self.vks[part.i - 1].invert(n2)) {
+            (Some(x), Some(y)) => (x, y),
+            _ => return false,
+        };
+
+        // a = c4^z / ci2^e, b = v^z / vk_i^e
+        let a = (c4.powm(&proof.z, n2) * ci2_inv.powm(&proof.e, n2)) % n2;
+        let b = (self.v.powm(&proof.z, n2) * vk_inv.powm(&proof.e, n2)) % n2;
+        proof.e == self.proof_challenge(part.i, &c4, &ci2, &a, &b)
+    }
+
+    /// Share holders whose partial decryption of c is missing a valid proof
+    pub fn find_cheaters(&self, c: &Mpz, parts: &[PartialDecryption]) -> Vec<usize> {
+        parts.iter().filter(|p| !self.verify_partial(c, p)).map(|p| p.i).collect()
+    }
+
+    /// Like `combine`, but first drops every partial decryption of c without
+    /// a valid proof. `Error::NotEnoughShares` if fewer than t valid ones remain.
+    pub fn combine_verified(&self, c: &Mpz, parts: &[PartialDecryption]) -> Result<Mpz> {
+        let valid: Vec<PartialDecryption> = parts.iter()
+            .filter(|p| self.verify_partial(c, p))
+            .cloned()
+            .collect();
+        self.combine(&valid)
+    }
+
+    /// Recover the plaintext from partial decryptions of at least t distinct
+    /// share holders, `Error::NotEnoughShares` if there are not enough of them
+    /// or some are duplicated or out of range, `Error::InvalidCiphertext` if a
+    /// partial decryption is not a unit mod n^2
+    pub fn combine(&self, parts: &[PartialDecryption]) -> Result<Mpz> {
+        let mut set: Vec<usize> = parts.iter().map(|p| p.i).collect();
+        set.sort();
+        set.dedup();
+        if set.len() < self.t || set.len() != parts.len() {
+            return Err(Error::NotEnoughShares);
+        }
+        if set.iter().any(|&i| i == 0 || i > self.l) {
+            return Err(Error::NotEnoughShares);
+        }
+        if parts.iter().any(|p| !self.pk.validate_ciphertext(&p.ci)) {
+            return Err(Error::InvalidCiphertext);
+        }
+
+        let set = &set[..self.t];
+        let n2 = &self.pk.n2;
+        let mut cc = Mpz::one();
+        for part in parts.iter().filter(|p| set.contains(&p.i)) {
+            let e = self.lagrange(set, part.i) * 2u64;
+            cc = (cc * powm_signed(&part.ci, &e, n2)?) % n2;
+        }
+
+        // cc = (1+n)^(4 delta^2 m)
+        let l = (cc - Mpz::one()) / &self.pk.n;
+        let four_delta2 = (&self.delta * &self.delta * 4u64).invert(&self.pk.n).ok_or(Error::NotInvertible)?;
+        Ok((l * four_delta2) % &self.pk.n)
+    }
+}
+
+impl KeyShare {
+
+    pub fn partial_decrypt(&self, tpk: &ThresholdPk, c: &Mpz) -> PartialDecryption {
+        let e = &tpk.delta * &self.si * 2u64;
+        PartialDecryption { i: self.i, ci: c.powm(&e, &tpk.pk.n2), proof: None }
+    }
+
+    /// Partial decryption together with a proof that it is correct
+    pub fn partial_decrypt_with_proof(&self, tpk: &ThresholdPk, rs: &mut dyn Randomness, c: &Mpz) -> PartialDecryption {
+        let mut part = self.partial_decrypt(tpk, c);
+        let n2 = &tpk.pk.n2;
+        let x = &tpk.delta * &self.si;
+        let c4 = c.powm(&Mpz::from(4u64), n2);
+        let ci2 = (&part.ci * &part.ci) % n2;
+
+        // delta * s_i < delta * n^2
+        let bits = tpk.delta.bit_length() + n2.bit_length() + CHALLENGE_BITS + STAT_BITS;
+        let r = random_mask(rs, bits);
+        let a = c4.powm(&r, n2);
+        let b = tpk.v.powm(&r, n2);
+        let e = tpk.proof_challenge(self.i, &c4, &ci2, &a, &b);
+        let z = r + &e * x;
+
+        part.proof = Some(DecryptionProof { e, z });
+        part
+    }
+}
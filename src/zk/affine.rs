@@ -0,0 +1,128 @@
+//! Proof of a correct homomorphic affine operation with hidden constants
+//!
+//! A party holding secret integers a and b turns a ciphertext c under the
+//! receiver's key pk0 into d = c^a * (1+n0)^b * rho^n0 mod n0^2, the
+//! rerandomized encryption of a*m + b. It commits to a and b by encrypting
+//! them under its own Paillier key pk1 and proves that d, x = Enc_pk1(a) and
+//! y = Enc_pk1(b) use the same a and b. This is the building block of
+//! multiplicative-to-additive share conversion.
+//!
+//! a and b are non-negative integers of at most `bits` bits. Responses are
+//! computed over the integers and their size is checked, which binds the
+//! same integers across both moduli as long as they are well below n0 and n1.
+
+use gmp::mpz::Mpz;
+use rng::Randomness;
+use error::{Error, Result};
+use paillier::PaiPk;
+use zk::{challenge, is_unit, random_mask, CHALLENGE_BITS, STAT_BITS};
+
+const TAG: &str = "paillier-affine-operation";
+
+/// Public result of the affine operation
+#[derive(Clone)]
+pub struct AffineStatement {
+    pub d : Mpz,    // c^a * Enc_pk0(b)
+    pub x : Mpz,    // Enc_pk1(a)
+    pub y : Mpz,    // Enc_pk1(b)
+}
+
+#[derive(Clone)]
+pub struct AffineProof {
+    pub ad : Mpz,
+    pub ax : Mpz,
+    pub ay : Mpz,
+    pub z1 : Mpz,
+    pub z2 : Mpz,
+    pub w  : Mpz,
+    pub wx : Mpz,
+    pub wy : Mpz,
+}
+
+fn mask_bits(bits: usize) -> usize {
+    bits + CHALLENGE_BITS + STAT_BITS
+}
+
+/// Responses must stay far enough below both moduli to bind integers
+fn bound_fits(pk0: &PaiPk, pk1: &PaiPk, bits: usize) -> bool {
+    let max = mask_bits(bits) + 2;
+    max < pk0.n.bit_length() && max < pk1.n.bit_length()
+}
+
+fn commitments(pk0: &PaiPk, pk1: &PaiPk, c: &Mpz, st: &AffineStatement, cmt: [&Mpz; 3], context: &[u8]) -> Mpz {
+    challenge(TAG, context, &[&pk0.n, &pk1.n, c, &st.d, &st.x, &st.y, cmt[0], cmt[1], cmt[2]])
+}
+
+impl AffineProof {
+
+    /// Compute d = c^a * Enc_pk0(b) for (a, b) = ab, commit to a and b under
+    /// pk1 and prove consistency. `Error::PlaintextOverflow` if a or b is
+    /// negative or longer than `bits`, or `bits` is too large for the moduli.
+    pub fn apply_and_prove(pk0: &PaiPk, pk1: &PaiPk, rs: &mut dyn Randomness, c: &Mpz, ab: (&Mpz, &Mpz), bits: usize, context: &[u8]) -> Result<(AffineStatement, AffineProof)> {
+        let (a, b) = ab;
+        if !is_unit(c, &pk0.n, &pk0.n2) {
+            return Err(Error::InvalidCiphertext);
+        }
+        if !bound_fits(pk0, pk1, bits) {
+            return Err(Error::PlaintextOverflow);
+        }
+        if *a < Mpz::zero() || *b < Mpz::zero() || a.bit_length() > bits || b.bit_length() > bits {
+            return Err(Error::PlaintextOverflow);
+        }
+
+        let rho = pk0.random_unit(rs);
+        let rho_x = pk1.random_unit(rs);
+        let rho_y = pk1.random_unit(rs);
+        let d = (c.powm(a, &pk0.n2) * pk0.encrypt_with_rn(b, &rho.powm(&pk0.n, &pk0.n2))) % &pk0.n2;
+        let x = pk1.encrypt_with_rn(a, &rho_x.powm(&pk1.n, &pk1.n2));
+        let y = pk1.encrypt_with_rn(b, &rho_y.powm(&pk1.n, &pk1.n2));
+        let st = AffineStatement { d, x, y };
+
+        let alpha = random_mask(rs, mask_bits(bits));
+        let beta = random_mask(rs, mask_bits(bits));
+        let r = pk0.random_unit(rs);
+        let rx = pk1.random_unit(rs);
+        let ry = pk1.random_unit(rs);
+        let ad = (c.powm(&alpha, &pk0.n2) * pk0.encrypt_with_rn(&beta, &r.powm(&pk0.n, &pk0.n2))) % &pk0.n2;
+        let ax = pk1.encrypt_with_rn(&alpha, &rx.powm(&pk1.n, &pk1.n2));
+        let ay = pk1.encrypt_with_rn(&beta, &ry.powm(&pk1.n, &pk1.n2));
+
+        let e = commitments(pk0, pk1, c, &st, [&ad, &ax, &ay], context);
+        let z1 = alpha + &e * a;
+        let z2 = beta + &e * b;
+        let w = (r * rho.powm(&e, &pk0.n)) % &pk0.n;
+        let wx = (rx * rho_x.powm(&e, &pk1.n)) % &pk1.n;
+        let wy = (ry * rho_y.powm(&e, &pk1.n)) % &pk1.n;
+
+        Ok((st, AffineProof { ad, ax, ay, z1, z2, w, wx, wy }))
+    }
+
+    pub fn verify(&self, pk0: &PaiPk, pk1: &PaiPk, c: &Mpz, st: &AffineStatement, bits: usize, context: &[u8]) -> bool {
+        if !bound_fits(pk0, pk1, bits) {
+            return false;
+        }
+        let units0 = [c, &st.d, &self.ad];
+        let units1 = [&st.x, &st.y, &self.ax, &self.ay];
+        if !units0.iter().all(|v| is_unit(v, &pk0.n, &pk0.n2)) || !units1.iter().all(|v| is_unit(v, &pk1.n, &pk1.n2)) {
+            return false;
+        }
+        if !is_unit(&self.w, &pk0.n, &pk0.n) || !is_unit(&self.wx, &pk1.n, &pk1.n) || !is_unit(&self.wy, &pk1.n, &pk1.n) {
+            return false;
+        }
+        // z = mask + e * secret < 2^(mask_bits + 1)
+        let zmax = mask_bits(bits) + 1;
+        if self.z1 < Mpz::zero() || self.z2 < Mpz::zero() || self.z1.bit_length() > zmax || self.z2.bit_length() > zmax {
+            return false;
+        }
+
+        let e = commitments(pk0, pk1, c, st, [&self.ad, &self.ax, &self.ay], context);
+        let lhs_d = (c.powm(&self.z1, &pk0.n2) * pk0.encrypt_with_rn(&self.z2, &self.w.powm(&pk0.n, &pk0.n2))) % &pk0.n2;
+        let rhs_d = (&self.ad * st.d.powm(&e, &pk0.n2)) % &pk0.n2;
+        let lhs_x = pk1.encrypt_with_rn(&self.z1, &self.wx.powm(&pk1.n, &pk1.n2));
+        let rhs_x = (&self.ax * st.x.powm(&e, &pk1.n2)) % &pk1.n2;
+        let lhs_y = pk1.encrypt_with_rn(&self.z2, &self.wy.powm(&pk1.n, &pk1.n2));
+        let rhs_y = (&self.ay * st.y.powm(&e, &pk1.n2)) % &pk1.n2;
+
+        lhs_d == rhs_d && lhs_x == rhs_x && lhs_y == rhs_y
+    }
+}
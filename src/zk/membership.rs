@@ -0,0 +1,137 @@
+//! Proofs that a Paillier ciphertext encrypts a member of a small public set
+//!
+//! Disjunctive (CDS) OR composition of n-th residuosity proofs: for each
+//! candidate s_i, c / (1+n)^s_i is an n-th residue exactly when c encrypts s_i.
+//! The prover answers the real branch and simulates all others.
+
+use gmp::mpz::Mpz;
+use rng::Randomness;
+use error::{Error, Result};
+use paillier::PaiPk;
+use zk::{challenge, challenge_mod, is_unit};
+
+const TAG: &str = "paillier-membership";
+
+#[derive(Clone)]
+pub struct MembershipProof {
+    pub a : Vec<Mpz>,
+    pub e : Vec<Mpz>,
+    pub z : Vec<Mpz>,
+}
+
+/// Proof that a ciphertext encrypts 0 or 1
+#[derive(Clone)]
+pub struct ZeroOneProof(pub MembershipProof);
+
+fn zero_one() -> [Mpz; 2] {
+    [Mpz::zero(), Mpz::one()]
+}
+
+/// u_i = c / (1+n)^s_i mod n^2, `Error::InvalidCiphertext` if c is not a unit
+fn branches(pk: &PaiPk, c: &Mpz, set: &[Mpz]) -> Result<Vec<Mpz>> {
+    if !is_unit(c, &pk.n, &pk.n2) {
+        return Err(Error::InvalidCiphertext);
+    }
+    set.iter().map(|s| {
+        let g = pk.raw_encrypt(&s.modulus(&pk.n)).invert(&pk.n2).ok_or(Error::NotInvertible)?;
+        Ok((c * g) % &pk.n2)
+    }).collect()
+}
+
+fn set_challenge(pk: &PaiPk, c: &Mpz, set: &[Mpz], a: &[Mpz], context: &[u8]) -> Mpz {
+    let mut values = vec![&pk.n, c];
+    values.extend(set.iter());
+    values.extend(a.iter());
+    challenge(TAG, context, &values)
+}
+
+impl MembershipProof {
+
+    /// Prove that c = (1+n)^m * r^n mod n^2 with m in set.
+    /// `Error::InvalidPlaintext` if m is not a member of the set.
+    pub fn prove(pk: &PaiPk, rs: &mut dyn Randomness, c: &Mpz, set: &[Mpz], m: &Mpz, r: &Mpz, context: &[u8]) -> Result<MembershipProof> {
+        let u = branches(pk, c, set)?;
+        if !is_unit(r, &pk.n, &pk.n) {
+            return Err(Error::InvalidRandomness);
+        }
+        let mm = m.modulus(&pk.n);
+        let real = set.iter().position(|s| s.modulus(&pk.n) == mm).ok_or(Error::InvalidPlaintext)?;
+        let t = challenge_mod();
+
+        let mut a = Vec::with_capacity(set.len());
+        let mut e = Vec::with_capacity(set.len());
+        let mut z = Vec::with_capacity(set.len());
+        let s = pk.random_unit(rs);
+        for (i, ui) in u.iter().enumerate() {
+            if i == real {
+                a.push(s.powm(&pk.n, &pk.n2));
+                e.push(Mpz::zero());
+                z.push(Mpz::zero());
+            } else {
+                // simulated branch: a_i = z_i^n / u_i^e_i
+                let ei = rs.urandom(&t);
+                let zi = pk.random_unit(rs);
+                let ui_inv = ui.invert(&pk.n2).ok_or(Error::NotInvertible)?;
+                let ai = (zi.powm(&pk.n, &pk.n2) * ui_inv.powm(&ei, &pk.n2)) % &pk.n2;
+                a.push(ai);
+                e.push(ei);
+                z.push(zi);
+            }
+        }
+
+        let e_all = set_challenge(pk, c, set, &a, context);
+        let e_fake = e.iter().fold(Mpz::zero(), |acc, ei| acc + ei);
+        e[real] = (e_all - e_fake).modulus(&t);
+        z[real] = (s * r.powm(&e[real], &pk.n)) % &pk.n;
+        Ok(MembershipProof { a, e, z })
+    }
+
+    /// Encrypt m and prove that it is a member of the set
+    pub fn encrypt_and_prove(pk: &PaiPk, rs: &mut dyn Randomness, set: &[Mpz], m: &Mpz, context: &[u8]) -> Result<(Mpz, MembershipProof)> {
+        let r = pk.random_unit(rs);
+        let c = pk.encrypt_with_rn(&m.modulus(&pk.n), &r.powm(&pk.n, &pk.n2));
+        MembershipProof::prove(pk, rs, &c, set, m, &r, context).map(|proof| (c, proof))
+    }
+
+    pub fn verify(&self, pk: &PaiPk, c: &Mpz, set: &[Mpz], context: &[u8]) -> bool {
+        let k = set.len();
+        if k == 0 || self.a.len() != k || self.e.len() != k || self.z.len() != k {
+            return false;
+        }
+        let u = match branches(pk, c, set) {
+            Ok(u) => u,
+            Err(_) => return false,
+        };
+        let t = challenge_mod();
+        let e_all = set_challenge(pk, c, set, &self.a, context);
+        let e_sum = self.e.iter().fold(Mpz::zero(), |acc, ei| acc + ei);
+        if e_sum.modulus(&t) != e_all {
+            return false;
+        }
+
+        (0..k).all(|i| {
+            is_unit(&self.a[i], &pk.n, &pk.n2) && is_unit(&self.z[i], &pk.n, &pk.n)
+                && self.e[i] >= Mpz::zero() && self.e[i] < t
+                && self.z[i].powm(&pk.n, &pk.n2) == (&self.a[i] * u[i].powm(&self.e[i], &pk.n2)) % &pk.n2
+        })
+    }
+}
+
+impl ZeroOneProof {
+
+    /// Prove that c = (1+n)^bit * r^n mod n^2
+    pub fn prove(pk: &PaiPk, rs: &mut dyn Randomness, c: &Mpz, bit: bool, r: &Mpz, context: &[u8]) -> Result<ZeroOneProof> {
+        let m = if bit { Mpz::one() } else { Mpz::zero() };
+        MembershipProof::prove(pk, rs, c, &zero_one(), &m, r, context).map(ZeroOneProof)
+    }
+
+    pub fn encrypt_and_prove(pk: &PaiPk, rs: &mut dyn Randomness, bit: bool, context: &[u8]) -> Result<(Mpz, ZeroOneProof)> {
+        let m = if bit { Mpz::one() } else { Mpz::zero() };
+        let (c, proof) = MembershipProof::encrypt_and_prove(pk, rs, &zero_one(), &m, context)?;
+        Ok((c, ZeroOneProof(proof)))
+    }
+
+    pub fn verify(&self, pk: &PaiPk, c: &Mpz, context: &[u8]) -> bool {
+        self.0.verify(pk, c, &zero_one(), context)
+    }
+}
@@ -0,0 +1,84 @@
+//! Zero-knowledge proofs for Paillier, made non-interactive with Fiat-Shamir
+
+use gmp::mpz::Mpz;
+use rng::Randomness;
+use sha2::{Digest, Sha256};
+
+pub mod affine;
+pub mod membership;
+pub mod modulus;
+pub mod plaintext;
+pub mod range;
+
+/// Bit length of Fiat-Shamir challenges
+pub const CHALLENGE_BITS: usize = 128;
+
+/// Extra bits of masking randomness in responses computed over the integers,
+/// the statistical distance to zero knowledge is about 2^-STAT_BITS
+pub const STAT_BITS: usize = 80;
+
+/// 0 < x < bound and gcd(x, n) = 1
+pub(crate) fn is_unit(x: &Mpz, n: &Mpz, bound: &Mpz) -> bool {
+    *x > Mpz::zero() && x < bound && x.gcd(n) == Mpz::one()
+}
+
+/// 2^j
+pub(crate) fn pow2(j: usize) -> Mpz {
+    let mut t = Mpz::zero();
+    t.setbit(j);
+    t
+}
+
+/// 2^CHALLENGE_BITS, the modulus for split challenges in OR proofs
+pub(crate) fn challenge_mod() -> Mpz {
+    pow2(CHALLENGE_BITS)
+}
+
+fn absorb(h: &mut Sha256, data: &[u8]) {
+    h.update((data.len() as u64).to_be_bytes());
+    h.update(data);
+}
+
+/// Hash a proof tag, a caller-supplied context and the public values of a
+/// statement into a challenge in [0, 2^CHALLENGE_BITS)
+pub fn challenge(tag: &str, context: &[u8], values: &[&Mpz]) -> Mpz {
+    let mut h = Sha256::new();
+    absorb(&mut h, tag.as_bytes());
+    absorb(&mut h, context);
+    for v in values {
+        let bytes: Vec<u8> = (*v).into();
+        absorb(&mut h, &bytes);
+    }
+    let digest = h.finalize();
+    Mpz::from(&digest[..CHALLENGE_BITS / 8])
+}
+
+/// Hash into [0, modulus), with 128 extra bits so the bias is negligible
+pub fn hash_to_mod(tag: &str, context: &[u8], values: &[&Mpz], modulus: &Mpz) -> Mpz {
+    let mut h = Sha256::new();
+    absorb(&mut h, tag.as_bytes());
+    absorb(&mut h, context);
+    for v in values {
+        let bytes: Vec<u8> = (*v).into();
+        absorb(&mut h, &bytes);
+    }
+    let seed = h.finalize();
+
+    let len = (modulus.bit_length() + 128).div_ceil(8);
+    let mut out = Vec::with_capacity(len + 32);
+    let mut counter = 0u32;
+    while out.len() < len {
+        let mut h = Sha256::new();
+        h.update(seed);
+        h.update(counter.to_be_bytes());
+        out.extend_from_slice(&h.finalize());
+        counter += 1;
+    }
+    Mpz::from(&out[..len]).modulus(modulus)
+}
+
+/// Uniform random integer of at most `bits` bits, used to mask secrets in
+/// responses computed over the integers
+pub fn random_mask(rs: &mut dyn Randomness, bits: usize) -> Mpz {
+    rs.urandom_2exp(bits as u64)
+}
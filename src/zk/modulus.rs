@@ -0,0 +1,166 @@
+//! Proofs that a Paillier modulus is well formed
+//!
+//! `SquareFreeProof` is the n-th root proof of Gennaro, Micciancio and Rabin:
+//! n-th roots of random elements exist only if gcd(n, phi(n)) = 1, so n has
+//! no square factor. With trial division up to SMALL_PRIME_BOUND each round
+//! has soundness error below 2^-16.
+//!
+//! `BlumProof` is the Paillier-Blum modulus proof from Canetti et al. (CGGMP):
+//! n = pq with p = q = 3 mod 4 and gcd(n, phi(n)) = 1.
+
+use gmp::mpz::Mpz;
+use rng::{small_odd_primes, Randomness};
+use error::{Error, Result};
+use paillier::{PaiPk, PaiSk};
+use zk::hash_to_mod;
+
+const SQUARE_FREE_TAG: &str = "paillier-modulus-square-free";
+const BLUM_TAG: &str = "paillier-modulus-blum";
+
+pub const SMALL_PRIME_BOUND: u64 = 1 << 16;
+pub const SQUARE_FREE_ROUNDS: usize = 8;
+pub const BLUM_ROUNDS: usize = 80;
+
+#[derive(Clone)]
+pub struct SquareFreeProof {
+    pub sigmas : Vec<Mpz>,
+}
+
+/// x^4 = (-1)^a * w^b * y and z^n = y for the i-th challenge y
+#[derive(Clone)]
+pub struct BlumRound {
+    pub x : Mpz,
+    pub a : bool,
+    pub b : bool,
+    pub z : Mpz,
+}
+
+#[derive(Clone)]
+pub struct BlumProof {
+    pub w      : Mpz,
+    pub rounds : Vec<BlumRound>,
+}
+
+/// Both proofs, as required by `PaiPk::import`
+#[derive(Clone)]
+pub struct ModulusProof {
+    pub square_free : SquareFreeProof,
+    pub blum        : BlumProof,
+}
+
+/// True when n <= SMALL_PRIME_BOUND or n has a prime factor below it
+pub fn has_small_factor(n: &Mpz) -> bool {
+    if *n <= Mpz::from(SMALL_PRIME_BOUND) {
+        return true;
+    }
+    !n.tstbit(0) || small_odd_primes().iter().any(|&p| (n % p).is_zero())
+}
+
+fn challenge_element(tag: &str, n: &Mpz, extra: &Mpz, i: usize, context: &[u8]) -> Mpz {
+    hash_to_mod(tag, context, &[n, extra, &Mpz::from(i as u64)], n)
+}
+
+/// y^(n^-1 mod lambda) mod n
+fn nth_root(pk: &PaiPk, sk: &PaiSk, y: &Mpz) -> Result<Mpz> {
+    let d = pk.n.invert(&sk.lambda).ok_or(Error::InvalidKey)?;
+    Ok(y.powm(&d, &pk.n))
+}
+
+/// Fourth root of a quadratic residue y modulo a prime p = 3 mod 4
+fn fourth_root_mod(y: &Mpz, p: &Mpz) -> Mpz {
+    let e = (p + Mpz::one()) / 4u64;
+    y.powm(&(&e * &e), p)
+}
+
+impl SquareFreeProof {
+
+    pub fn prove(pk: &PaiPk, sk: &PaiSk, context: &[u8]) -> Result<SquareFreeProof> {
+        let sigmas = (0..SQUARE_FREE_ROUNDS).map(|i| {
+            let rho = challenge_element(SQUARE_FREE_TAG, &pk.n, &Mpz::zero(), i, context);
+            nth_root(pk, sk, &rho)
+        }).collect::<Result<Vec<Mpz>>>()?;
+        Ok(SquareFreeProof { sigmas })
+    }
+
+    pub fn verify(&self, n: &Mpz, context: &[u8]) -> bool {
+        if self.sigmas.len() != SQUARE_FREE_ROUNDS || has_small_factor(n) {
+            return false;
+        }
+        self.sigmas.iter().enumerate().all(|(i, sigma)| {
+            let rho = challenge_element(SQUARE_FREE_TAG, n, &Mpz::zero(), i, context);
+            rho.gcd(n) == Mpz::one() && *sigma > Mpz::zero() && sigma < n
+                && sigma.powm(n, n) == rho
+        })
+    }
+}
+
+impl BlumProof {
+
+    /// `Error::InvalidKey` unless both prime factors are 3 mod 4
+    pub fn prove(pk: &PaiPk, sk: &PaiSk, rs: &mut dyn Randomness, context: &[u8]) -> Result<BlumProof> {
+        let three = Mpz::from(3u64);
+        if &sk.p % 4u64 != three || &sk.q % 4u64 != three {
+            return Err(Error::InvalidKey);
+        }
+        let n = &pk.n;
+        let mut w = rs.urandom(n);
+        while w.jacobi(n) != -1 {
+            w = rs.urandom(n);
+        }
+
+        let minus_one = n - Mpz::one();
+        let rounds = (0..BLUM_ROUNDS).map(|i| {
+            let y = challenge_element(BLUM_TAG, n, &w, i, context);
+            let z = nth_root(pk, sk, &y)?;
+
+            // exactly one of y, -y, wy, -wy is a square mod p and mod q
+            let mut round = None;
+            for &(a, b) in [(false, false), (true, false), (false, true), (true, true)].iter() {
+                let mut yy = y.clone();
+                if a { yy = (yy * &minus_one) % n; }
+                if b { yy = (yy * &w) % n; }
+                if yy.legendre(&sk.p) == 1 && yy.legendre(&sk.q) == 1 {
+                    let xp = fourth_root_mod(&(&yy % &sk.p), &sk.p);
+                    let xq = fourth_root_mod(&(&yy % &sk.q), &sk.q);
+                    let u = ((xq - &xp) * &sk.pinv).modulus(&sk.q);
+                    round = Some(BlumRound { x: xp + u * &sk.p, a, b, z: z.clone() });
+                    break;
+                }
+            }
+            round.ok_or(Error::InvalidKey)
+        }).collect::<Result<Vec<BlumRound>>>()?;
+
+        Ok(BlumProof { w, rounds })
+    }
+
+    pub fn verify(&self, n: &Mpz, context: &[u8]) -> bool {
+        if self.rounds.len() != BLUM_ROUNDS {
+            return false;
+        }
+        if n.is_zero() || !n.tstbit(0) || n.probab_prime_p(40) || self.w.jacobi(n) != -1 {
+            return false;
+        }
+
+        let minus_one = n - Mpz::one();
+        self.rounds.iter().enumerate().all(|(i, round)| {
+            let y = challenge_element(BLUM_TAG, n, &self.w, i, context);
+            let mut yy = y.clone();
+            if round.a { yy = (yy * &minus_one) % n; }
+            if round.b { yy = (yy * &self.w) % n; }
+            round.z.powm(n, n) == y && round.x.powm(&Mpz::from(4u64), n) == yy
+        })
+    }
+}
+
+impl ModulusProof {
+
+    pub fn prove(pk: &PaiPk, sk: &PaiSk, rs: &mut dyn Randomness, context: &[u8]) -> Result<ModulusProof> {
+        let square_free = SquareFreeProof::prove(pk, sk, context)?;
+        let blum = BlumProof::prove(pk, sk, rs, context)?;
+        Ok(ModulusProof { square_free, blum })
+    }
+
+    pub fn verify(&self, n: &Mpz, context: &[u8]) -> bool {
+        self.square_free.verify(n, context) && self.blum.verify(n, context)
+    }
+}
@@ -0,0 +1,63 @@
+//! Proof of knowledge of the plaintext of a Paillier ciphertext
+//!
+//! Sigma protocol for knowledge of m and r with c = (1+n)^m * r^n mod n^2,
+//! bound to a caller-supplied context string.
+
+use gmp::mpz::Mpz;
+use rng::Randomness;
+use error::{Error, Result};
+use paillier::PaiPk;
+use zk::{challenge, is_unit};
+
+const TAG: &str = "paillier-plaintext-knowledge";
+
+#[derive(Clone)]
+pub struct PlaintextProof {
+    pub a : Mpz,
+    pub z : Mpz,
+    pub w : Mpz,
+}
+
+impl PlaintextProof {
+
+    /// Prove knowledge of m and r for c = (1+n)^m * r^n mod n^2
+    pub fn prove(pk: &PaiPk, rs: &mut dyn Randomness, c: &Mpz, m: &Mpz, r: &Mpz, context: &[u8]) -> Result<PlaintextProof> {
+        if !is_unit(c, &pk.n, &pk.n2) {
+            return Err(Error::InvalidCiphertext);
+        }
+        if !is_unit(r, &pk.n, &pk.n) {
+            return Err(Error::InvalidRandomness);
+        }
+        let x = rs.urandom(&pk.n);
+        let s = pk.random_unit(rs);
+        let a = pk.encrypt_with_rn(&x, &s.powm(&pk.n, &pk.n2));
+        let e = challenge(TAG, context, &[&pk.n, c, &a]);
+
+        // (1+n)^n = 1 mod n^2, so the carry of x + e*m past n needs no correction
+        let z = (x + &e * m).modulus(&pk.n);
+        let w = (s * r.powm(&e, &pk.n)) % &pk.n;
+        Ok(PlaintextProof { a, z, w })
+    }
+
+    /// Encrypt m and prove knowledge of the plaintext
+    pub fn encrypt_and_prove(pk: &PaiPk, rs: &mut dyn Randomness, m: &Mpz, context: &[u8]) -> Result<(Mpz, PlaintextProof)> {
+        let r = pk.random_unit(rs);
+        let c = pk.encrypt_with_rn(m, &r.powm(&pk.n, &pk.n2));
+        let proof = PlaintextProof::prove(pk, rs, &c, m, &r, context)?;
+        Ok((c, proof))
+    }
+
+    pub fn verify(&self, pk: &PaiPk, c: &Mpz, context: &[u8]) -> bool {
+        if !is_unit(c, &pk.n, &pk.n2) || !is_unit(&self.a, &pk.n, &pk.n2) {
+            return false;
+        }
+        if self.z < Mpz::zero() || self.z >= pk.n || !is_unit(&self.w, &pk.n, &pk.n) {
+            return false;
+        }
+
+        let e = challenge(TAG, context, &[&pk.n, c, &self.a]);
+        let lhs = pk.encrypt_with_rn(&self.z, &self.w.powm(&pk.n, &pk.n2));
+        let rhs = (&self.a * c.powm(&e, &pk.n2)) % &pk.n2;
+        lhs == rhs
+    }
+}
@@ -0,0 +1,197 @@
+//! Range proofs that a Paillier ciphertext encrypts a value in [0, B)
+//!
+//! Bit decomposition: with k = bit_length(B - 1), the prover encrypts the k
+//! bits of m, proves each one encrypts 0 or 1, and proves that c divided by
+//! prod c_j^(2^j) encrypts 0. The same is done for B - 1 - m, which is
+//! computed homomorphically from c, unless B is a power of two. Both values
+//! lying in [0, 2^k) with 2^(k+1) < n implies 0 <= m < B.
+
+use gmp::mpz::Mpz;
+use rng::Randomness;
+use error::{Error, Result};
+use paillier::PaiPk;
+use zk::{challenge, is_unit, pow2};
+use zk::membership::ZeroOneProof;
+
+const ZERO_TAG: &str = "paillier-range-zero";
+
+/// Proof that a ciphertext is an n-th residue mod n^2, i.e. encrypts 0
+#[derive(Clone)]
+pub struct ZeroProof {
+    pub a : Mpz,
+    pub z : Mpz,
+}
+
+/// Encrypted bits of a value, with a proof for each bit and a proof that
+/// they add up to the value
+#[derive(Clone)]
+pub struct BitDecomposition {
+    pub bits       : Vec<Mpz>,
+    pub bit_proofs : Vec<ZeroOneProof>,
+    pub sum        : ZeroProof,
+}
+
+#[derive(Clone)]
+pub struct RangeProof {
+    pub lower : BitDecomposition,           // m in [0, 2^k)
+    pub upper : Option<BitDecomposition>,   // B - 1 - m in [0, 2^k)
+}
+
+/// k = bit_length(B - 1), `None` if B is out of the supported range
+fn range_bits(pk: &PaiPk, bound: &Mpz) -> Option<usize> {
+    if *bound < Mpz::one() {
+        return None;
+    }
+    let k = (bound - Mpz::one()).bit_length();
+    if k + 2 > pk.n.bit_length() {
+        return None;
+    }
+    Some(k)
+}
+
+impl ZeroProof {
+
+    /// Prove d = rho^n mod n^2
+    pub fn prove(pk: &PaiPk, rs: &mut dyn Randomness, d: &Mpz, rho: &Mpz, context: &[u8]) -> ZeroProof {
+        let s = pk.random_unit(rs);
+        let a = s.powm(&pk.n, &pk.n2);
+        let e = challenge(ZERO_TAG, context, &[&pk.n, d, &a]);
+        let z = (s * rho.powm(&e, &pk.n)) % &pk.n;
+        ZeroProof { a, z }
+    }
+
+    pub fn verify(&self, pk: &PaiPk, d: &Mpz, context: &[u8]) -> bool {
+        if !is_unit(d, &pk.n, &pk.n2) || !is_unit(&self.a, &pk.n, &pk.n2) || !is_unit(&self.z, &pk.n, &pk.n) {
+            return false;
+        }
+        let e = challenge(ZERO_TAG, context, &[&pk.n, d, &self.a]);
+        self.z.powm(&pk.n, &pk.n2) == (&self.a * d.powm(&e, &pk.n2)) % &pk.n2
+    }
+}
+
+impl BitDecomposition {
+
+    /// c = (1+n)^m * r^n mod n^2 with 0 <= m < 2^k
+    fn prove(pk: &PaiPk, rs: &mut dyn Randomness, c: &Mpz, m: &Mpz, r: &Mpz, k: usize, context: &[u8]) -> Result<BitDecomposition> {
+        let mut bits = Vec::with_capacity(k);
+        let mut bit_proofs = Vec::with_capacity(k);
+        let mut prod = Mpz::one();      // prod c_j^(2^j)
+        let mut rprod = Mpz::one();     // prod r_j^(2^j)
+        for j in 0..k {
+            let bit = m.tstbit(j);
+            let rj = pk.random_unit(rs);
+            let cj = pk.encrypt_with_rn(&Mpz::from(bit as u64), &rj.powm(&pk.n, &pk.n2));
+            let ctx = BitDecomposition::bit_context(context, c, j);
+            bit_proofs.push(ZeroOneProof::prove(pk, rs, &cj, bit, &rj, &ctx)?);
+            prod = (prod * cj.powm(&pow2(j), &pk.n2)) % &pk.n2;
+            rprod = (rprod * rj.powm(&pow2(j), &pk.n)) % &pk.n;
+            bits.push(cj);
+        }
+
+        let d = (c * prod.invert(&pk.n2).ok_or(Error::NotInvertible)?) % &pk.n2;
+        let rho = (r * rprod.invert(&pk.n).ok_or(Error::NotInvertible)?) % &pk.n;
+        let sum = ZeroProof::prove(pk, rs, &d, &rho, &BitDecomposition::bit_context(context, c, k));
+        Ok(BitDecomposition { bits, bit_proofs, sum })
+    }
+
+    fn verify(&self, pk: &PaiPk, c: &Mpz, k: usize, context: &[u8]) -> bool {
+        if self.bits.len() != k || self.bit_proofs.len() != k {
+            return false;
+        }
+        let mut prod = Mpz::one();
+        for j in 0..k {
+            let ctx = BitDecomposition::bit_context(context, c, j);
+            if !self.bit_proofs[j].verify(pk, &self.bits[j], &ctx) {
+                return false;
+            }
+            prod = (prod * self.bits[j].powm(&pow2(j), &pk.n2)) % &pk.n2;
+        }
+        let d = match prod.invert(&pk.n2) {
+            Some(inv) => (c * inv) % &pk.n2,
+            None => return false,
+        };
+        self.sum.verify(pk, &d, &BitDecomposition::bit_context(context, c, k))
+    }
+
+    /// Bind every sub-proof to the caller context, the ciphertext and its position
+    fn bit_context(context: &[u8], c: &Mpz, j: usize) -> Vec<u8> {
+        let mut ctx = context.to_vec();
+        let cb: Vec<u8> = c.into();
+        ctx.extend_from_slice(&cb);
+        ctx.extend_from_slice(&(j as u64).to_be_bytes());
+        ctx
+    }
+}
+
+impl RangeProof {
+
+    /// Prove that c = (1+n)^m * r^n mod n^2 encrypts m in [0, bound).
+    /// `Error::InvalidPlaintext` if m is not in the range or the bound is not
+    /// below n/2.
+    pub fn prove(pk: &PaiPk, rs: &mut dyn Randomness, c: &Mpz, m: &Mpz, r: &Mpz, bound: &Mpz, context: &[u8]) -> Result<RangeProof> {
+        let k = range_bits(pk, bound).ok_or(Error::InvalidPlaintext)?;
+        if *m < Mpz::zero() || m >= bound {
+            return Err(Error::InvalidPlaintext);
+        }
+        if !is_unit(c, &pk.n, &pk.n2) {
+            return Err(Error::InvalidCiphertext);
+        }
+        if !is_unit(r, &pk.n, &pk.n) {
+            return Err(Error::InvalidRandomness);
+        }
+
+        let lower = BitDecomposition::prove(pk, rs, c, m, r, k, &RangeProof::side_context(context, 0))?;
+        let upper = if *bound == pow2(k) {
+            None
+        } else {
+            let (cu, mu) = RangeProof::upper_value(pk, c, bound, m).ok_or(Error::InvalidCiphertext)?;
+            let ru = r.invert(&pk.n).ok_or(Error::InvalidRandomness)?;
+            Some(BitDecomposition::prove(pk, rs, &cu, &mu, &ru, k, &RangeProof::side_context(context, 1))?)
+        };
+        Ok(RangeProof { lower, upper })
+    }
+
+    /// Encrypt m and prove that it lies in [0, bound)
+    pub fn encrypt_and_prove(pk: &PaiPk, rs: &mut dyn Randomness, m: &Mpz, bound: &Mpz, context: &[u8]) -> Result<(Mpz, RangeProof)> {
+        let r = pk.random_unit(rs);
+        let c = pk.encrypt_with_rn(m, &r.powm(&pk.n, &pk.n2));
+        RangeProof::prove(pk, rs, &c, m, &r, bound, context).map(|proof| (c, proof))
+    }
+
+    pub fn verify(&self, pk: &PaiPk, c: &Mpz, bound: &Mpz, context: &[u8]) -> bool {
+        let k = match range_bits(pk, bound) {
+            Some(k) => k,
+            None => return false,
+        };
+        if !self.lower.verify(pk, c, k, &RangeProof::side_context(context, 0)) {
+            return false;
+        }
+        match (*bound == pow2(k), self.upper.as_ref()) {
+            (true, None) => true,
+            (false, Some(upper)) => {
+                if !is_unit(c, &pk.n, &pk.n2) {
+                    return false;
+                }
+                match RangeProof::upper_value(pk, c, bound, &Mpz::zero()) {
+                    Some((cu, _)) => upper.verify(pk, &cu, k, &RangeProof::side_context(context, 1)),
+                    None => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Encryption of B - 1 - m computed from c, with randomness r^-1.
+    /// `None` if c is not invertible.
+    fn upper_value(pk: &PaiPk, c: &Mpz, bound: &Mpz, m: &Mpz) -> Option<(Mpz, Mpz)> {
+        let bm1 = bound - Mpz::one();
+        let cu = (pk.raw_encrypt(&bm1) * c.invert(&pk.n2)?) % &pk.n2;
+        Some((cu, bm1 - m))
+    }
+
+    fn side_context(context: &[u8], side: u8) -> Vec<u8> {
+        let mut ctx = context.to_vec();
+        ctx.push(side);
+        ctx
+    }
+}